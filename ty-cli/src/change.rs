@@ -0,0 +1,105 @@
+//! `ty edit` and `ty retract`: change thank-yous sent from this machine, with
+//! the edit tokens kept in the ledger.
+
+use dialoguer::Confirm;
+use serde_json::json;
+use ty_client::blocking::Client;
+use ty_lib::NoteEdit;
+
+use crate::error::Error;
+use crate::ledger::{self, Entry};
+use crate::output::Output;
+
+/// The ledger entry of the note with this id, or of the last one that can be
+/// changed if no id is given.
+pub fn find(id: Option<i64>) -> Result<Entry, Error> {
+    let mut entries = ledger::read()?.into_iter().rev();
+
+    let entry = match id {
+        Some(id) => entries
+            .find(|entry| entry.id == Some(id) && entry.server == crate::API_ENDPOINT)
+            .ok_or_else(|| {
+                Error::Aborted(format!("The note {} wasn't sent from this machine.", id))
+            })?,
+        None => entries.find(Entry::is_changeable).ok_or_else(|| {
+            Error::Aborted("There is no thank-you in the ledger that can be changed.".to_string())
+        })?,
+    };
+
+    if entry.retracted {
+        return Err(Error::Aborted(
+            "This thank-you was retracted already.".to_string(),
+        ));
+    }
+    if entry.token.is_none() {
+        return Err(Error::Aborted(
+            "This thank-you was sent before notes could be changed.".to_string(),
+        ));
+    }
+    Ok(entry)
+}
+
+pub fn edit(
+    client: &Client,
+    entry: &Entry,
+    note: Option<String>,
+    output: &Output,
+) -> Result<(), Error> {
+    let (id, token) = id_and_token(entry);
+    client.edit_note(id, token, &NoteEdit { note: note.clone() })?;
+    ledger::amend(id, |entry| entry.note = note.clone());
+
+    output.json(&json!({ "edited": { "id": id, "program": entry.program, "note": note } }));
+    output.text(&format!("Changed your note for {}.", entry.program));
+    Ok(())
+}
+
+pub fn retract(client: &Client, entry: &Entry, yes: bool, output: &Output) -> Result<(), Error> {
+    let (id, token) = id_and_token(entry);
+    if !yes {
+        confirm(entry)?;
+    }
+
+    client.retract_note(id, token)?;
+    ledger::amend(id, |entry| entry.retracted = true);
+
+    output.json(&json!({ "retracted": { "id": id, "program": entry.program } }));
+    output.text(&format!("Retracted your thank-you to {}.", entry.program));
+    Ok(())
+}
+
+fn id_and_token(entry: &Entry) -> (i64, &str) {
+    match (entry.id, entry.token.as_deref()) {
+        (Some(id), Some(token)) => (id, token),
+        _ => unreachable!("find only returns entries with id and token"),
+    }
+}
+
+fn confirm(entry: &Entry) -> Result<(), Error> {
+    if !atty::is(atty::Stream::Stdin) {
+        return Err(Error::Aborted(
+            "Not asking for confirmation outside of a terminal, use --yes to retract anyway."
+                .to_string(),
+        ));
+    }
+
+    let retract = Confirm::new()
+        .with_prompt(format!(
+            "Retract your thank-you to {} from {}?",
+            entry.program,
+            entry
+                .sent_at
+                .with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+        ))
+        .default(false)
+        .interact()?;
+
+    if retract {
+        Ok(())
+    } else {
+        Err(Error::Aborted(
+            "Aborting, nothing was retracted.".to_string(),
+        ))
+    }
+}
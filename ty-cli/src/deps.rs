@@ -0,0 +1,248 @@
+//! `ty deps`: thank every dependency of the project in the current directory.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use dialoguer::Confirm;
+use serde_json::{json, Value};
+use ty_client::{blocking::Client, FieldErrors, NoteResult};
+use ty_lib::ThankYouMessage;
+
+use crate::error::Error;
+use crate::ledger;
+use crate::output::Output;
+
+/// Reads the names of the dependencies from a lock file.
+type Parse = fn(&str) -> Vec<String>;
+
+/// The lock files `ty deps` understands.
+const LOCK_FILES: &[(&str, Parse)] = &[
+    ("Cargo.lock", cargo_lock),
+    ("package-lock.json", package_lock),
+    ("requirements.txt", requirements_txt),
+    ("go.sum", go_sum),
+];
+
+pub struct Options {
+    pub output: Output,
+    pub note: Option<String>,
+    /// Don't ask before sending.
+    pub yes: bool,
+}
+
+pub fn thank(client: &Client, dir: &Path, options: &Options) -> Result<(), Error> {
+    let mut found = Vec::new();
+    let mut programs = BTreeSet::new();
+    for (file, parse) in LOCK_FILES {
+        if let Ok(content) = fs::read_to_string(dir.join(file)) {
+            found.push(*file);
+            programs.extend(parse(&content));
+        }
+    }
+
+    if found.is_empty() {
+        let files: Vec<&str> = LOCK_FILES.iter().map(|(file, _)| *file).collect();
+        return Err(Error::Aborted(format!(
+            "Found none of {} in this directory.",
+            files.join(", ")
+        )));
+    }
+    if programs.is_empty() {
+        return Err(Error::Aborted(format!(
+            "Found no dependencies in {}.",
+            found.join(", ")
+        )));
+    }
+
+    // one bad name, e.g. a too long npm package, would fail the whole batch
+    let (messages, skipped): (Vec<ThankYouMessage>, Vec<ThankYouMessage>) = programs
+        .into_iter()
+        .map(|program| ThankYouMessage {
+            program,
+            note: options.note.clone(),
+            ..Default::default()
+        })
+        .partition(|message| ty_client::validate(message).is_ok());
+    for message in &skipped {
+        eprintln!("Skipping {}, it can't be thanked.", message.program);
+    }
+    if messages.is_empty() {
+        return Err(Error::Aborted("Nothing left to thank.".to_string()));
+    }
+
+    if !options.yes {
+        confirm(&messages, &found)?;
+    }
+
+    send(client, &messages, &options.output)
+}
+
+/// Sends the messages as batches and remembers the ones that got through. The
+/// ones the server turned down end up in the error.
+pub fn send(client: &Client, messages: &[ThankYouMessage], output: &Output) -> Result<(), Error> {
+    let results = client.post_notes(messages)?;
+
+    let mut sent = Vec::new();
+    let mut rejected = FieldErrors::new();
+    for (message, result) in messages.iter().zip(results) {
+        match result {
+            NoteResult::Created(created) => sent.push((message, created)),
+            NoteResult::Invalid(fields) => {
+                let reasons = fields.values().flatten();
+                rejected.insert(
+                    message.program.clone(),
+                    reasons
+                        .map(|reason| format!("{}: {}", message.program, reason))
+                        .collect(),
+                );
+            }
+        }
+    }
+    ledger::remember(
+        sent.iter()
+            .map(|(message, created)| (*message, created.as_ref())),
+    );
+
+    let sent: Vec<&ThankYouMessage> = sent.into_iter().map(|(message, _)| message).collect();
+    output.json(&json!({ "sent": sent }));
+    output.text(&format!("Sent {} thank-yous, cheers!", sent.len()));
+
+    if rejected.is_empty() {
+        Ok(())
+    } else {
+        Err(ty_client::Error::Validation(rejected).into())
+    }
+}
+
+fn confirm(messages: &[ThankYouMessage], found: &[&str]) -> Result<(), Error> {
+    if !atty::is(atty::Stream::Stdin) {
+        return Err(Error::Aborted(
+            "Not asking for confirmation outside of a terminal, use --yes to send anyway."
+                .to_string(),
+        ));
+    }
+
+    let programs: Vec<&str> = messages.iter().map(|m| m.program.as_str()).collect();
+    eprintln!("{}\n", programs.join(", "));
+    let send = Confirm::new()
+        .with_prompt(format!(
+            "Thank these {} dependencies from {}?",
+            messages.len(),
+            found.join(", ")
+        ))
+        .default(false)
+        .interact()?;
+
+    if send {
+        Ok(())
+    } else {
+        Err(Error::Aborted("Aborting, nothing was sent.".to_string()))
+    }
+}
+
+/// Packages from a registry or git, the project's own crates have no source.
+fn cargo_lock(content: &str) -> Vec<String> {
+    let mut programs = Vec::new();
+    for package in content.split("[[package]]").skip(1) {
+        let mut name = None;
+        let mut has_source = false;
+        for line in package.lines() {
+            if let Some(value) = line.strip_prefix("name = ") {
+                name = Some(value.trim_matches('"').to_string());
+            } else if line.starts_with("source = ") {
+                has_source = true;
+            }
+        }
+        if let (Some(name), true) = (name, has_source) {
+            programs.push(name);
+        }
+    }
+    programs
+}
+
+/// Understands the `packages` of lockfile version 2 and 3 as well as the
+/// nested `dependencies` of version 1.
+fn package_lock(content: &str) -> Vec<String> {
+    fn nested(dependencies: &Value, programs: &mut Vec<String>) {
+        if let Some(dependencies) = dependencies.as_object() {
+            for (name, dependency) in dependencies {
+                programs.push(name.to_string());
+                nested(&dependency["dependencies"], programs);
+            }
+        }
+    }
+
+    let lock: Value = match serde_json::from_str(content) {
+        Ok(lock) => lock,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut programs = Vec::new();
+    match lock["packages"].as_object() {
+        Some(packages) => programs.extend(
+            packages
+                .keys()
+                .filter_map(|path| path.rsplit("node_modules/").next())
+                .filter(|name| !name.is_empty())
+                .map(String::from),
+        ),
+        None => nested(&lock["dependencies"], &mut programs),
+    }
+    programs
+}
+
+fn requirements_txt(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        // options like `-r other.txt` or `--index-url`
+        .filter(|line| !line.is_empty() && !line.starts_with('-'))
+        .filter_map(|line| {
+            line.split(|c: char| "=<>!~[;@ ".contains(c))
+                .next()
+                .map(String::from)
+        })
+        .collect()
+}
+
+/// Go modules are named by their path, the last part of it is the name people
+/// know them by, e.g. `cobra` for `github.com/spf13/cobra`.
+fn go_sum(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .filter_map(|module| {
+            module
+                .rsplit('/')
+                .find(|part| !is_major_version(part))
+                .map(String::from)
+        })
+        .collect()
+}
+
+/// The `v2` in `github.com/go-chi/chi/v5`.
+fn is_major_version(part: &str) -> bool {
+    part.len() > 1 && part.starts_with('v') && part[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+#[test]
+fn lock_files_are_read() {
+    let cargo = "version = 3\n\n[[package]]\nname = \"ty\"\nversion = \"0.2.1\"\n\n[[package]]\nname = \"clap\"\nversion = \"2.33.3\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n";
+    assert_eq!(cargo_lock(cargo), vec!["clap"]);
+
+    let npm_v1 =
+        r#"{"dependencies":{"react":{"version":"17.0.1","dependencies":{"loose-envify":{}}}}}"#;
+    assert_eq!(package_lock(npm_v1), vec!["react", "loose-envify"]);
+    let npm_v2 =
+        r#"{"packages":{"":{},"node_modules/@babel/core":{},"node_modules/a/node_modules/b":{}}}"#;
+    assert_eq!(package_lock(npm_v2), vec!["@babel/core", "b"]);
+
+    let pip =
+        "# comment\n-r base.txt\nrequests==2.25.1\nDjango>=3.1 ; python_version > '3'\nblack[d]\n";
+    assert_eq!(requirements_txt(pip), vec!["requests", "Django", "black"]);
+
+    let go =
+        "github.com/spf13/cobra v1.1.1 h1:abc=\ngithub.com/go-chi/chi/v5 v5.0.0/go.mod h1:def=\n";
+    assert_eq!(go_sum(go), vec!["cobra", "chi"]);
+}
@@ -0,0 +1,110 @@
+//! Writing the note in $EDITOR, like git does for commit messages.
+
+use std::env;
+use std::fs;
+use std::process::Command;
+
+use ty_client::{validate, FieldErrors};
+use ty_lib::{ThankYouMessage, MAX_NOTE_LENGTH};
+
+use crate::error::Error;
+
+/// Opens the editor, starting with `note`, until the note passes validation or
+/// is left empty, which aborts.
+pub fn compose_note(program: &str, note: &str) -> Result<String, Error> {
+    // created exclusively and only readable by us, removed when dropped
+    let file = tempfile::Builder::new()
+        .prefix("TY_NOTE_")
+        .suffix(".txt")
+        .tempfile()?;
+    let path = file.path();
+    let mut note = note.to_string();
+    let mut problems = FieldErrors::new();
+
+    loop {
+        fs::write(path, template(program, &note, &problems))?;
+        let edited = open_editor(path).and_then(|_| Ok(fs::read_to_string(path)?));
+
+        note = strip_comments(&edited?);
+        if note.is_empty() {
+            return Err(Error::Aborted(
+                "Aborting thank you due to empty note.".to_string(),
+            ));
+        }
+
+        let message = ThankYouMessage {
+            program: program.to_string(),
+            note: Some(note.clone()),
+            ..Default::default()
+        };
+        match validate(&message) {
+            Ok(()) => return Ok(note),
+            Err(ty_client::Error::Validation(errors)) => problems = errors,
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+fn open_editor(path: &std::path::Path) -> Result<(), Error> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| default_editor().to_string());
+
+    // editors are often configured with arguments, like `code --wait`
+    let mut words = editor.split_whitespace();
+    let program = match words.next() {
+        Some(program) => program,
+        None => default_editor(),
+    };
+    let status = Command::new(program).args(words).arg(path).status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        // like vim's :cq
+        Err(Error::Aborted(format!(
+            "Aborting thank you, the editor '{}' exited with {}.",
+            editor, status
+        )))
+    }
+}
+
+fn default_editor() -> &'static str {
+    if cfg!(windows) {
+        "notepad"
+    } else {
+        "vi"
+    }
+}
+
+fn template(program: &str, note: &str, problems: &FieldErrors) -> String {
+    let mut template = format!("{}\n\n", note);
+    for message in problems.values().flatten() {
+        template.push_str(&format!("# {}\n", message));
+    }
+    template.push_str(&format!(
+        "# Write your thank-you note for {}. Lines starting with '#' are\n\
+         # ignored, an empty note aborts. Notes can be up to {} characters long.\n",
+        program, MAX_NOTE_LENGTH
+    ));
+    template
+}
+
+fn strip_comments(text: &str) -> String {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect::<Vec<&str>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+#[test]
+fn comments_are_stripped() {
+    let text = "Thanks for the\nhelpful errors!\n\n# Write your note\n# more comments\n";
+    assert_eq!(strip_comments(text), "Thanks for the\nhelpful errors!");
+    assert_eq!(
+        strip_comments(&template("rustc", "", &FieldErrors::new())),
+        ""
+    );
+}
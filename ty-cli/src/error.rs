@@ -0,0 +1,34 @@
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Talking to the server didn't work out.
+    Client(ty_client::Error),
+    /// The user backed out, e.g. by leaving the note empty.
+    Aborted(String),
+    /// Something on this machine went wrong, e.g. the editor didn't start.
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Client(err) => write!(f, "{}", err),
+            Error::Aborted(reason) => write!(f, "{}", reason),
+            Error::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<ty_client::Error> for Error {
+    fn from(err: ty_client::Error) -> Self {
+        Error::Client(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
@@ -0,0 +1,285 @@
+//! Sponsor links found on this machine: in the manifest of an installed crate
+//! or npm package, or the `.github/FUNDING.yml` of a repository. Nothing of it
+//! is sent, unless asked to with `--share-links`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// As many as the server accepts with a thank-you.
+const MAX_FUNDING: usize = 5;
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct Links {
+    pub funding: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+}
+
+impl Links {
+    /// Only links the server would accept are kept, the first repository wins.
+    fn add(&mut self, other: Links) {
+        for link in other.funding {
+            if is_link(&link) && !self.funding.contains(&link) && self.funding.len() < MAX_FUNDING {
+                self.funding.push(link);
+            }
+        }
+        if self.repository.is_none() {
+            self.repository = other.repository.filter(|link| is_link(link));
+        }
+    }
+}
+
+/// Looks for the program in the cargo registry and the npm package folders,
+/// and in the repository, if one is given.
+pub fn discover(program: &str, repo: Option<&Path>) -> Links {
+    let mut links = Links::default();
+
+    if let Some(content) = cargo_manifest(program).and_then(|path| fs::read_to_string(path).ok()) {
+        links.add(cargo_toml(&content));
+    }
+
+    let package = node_modules(repo)
+        .into_iter()
+        .find_map(|dir| fs::read_to_string(dir.join(program).join("package.json")).ok());
+    if let Some(content) = package {
+        links.add(package_json(&content));
+    }
+
+    if let Some(repo) = repo {
+        if let Ok(content) = fs::read_to_string(repo.join(".github").join("FUNDING.yml")) {
+            links.add(Links {
+                funding: funding_yml(&content),
+                repository: None,
+            });
+        }
+    }
+
+    links
+}
+
+fn is_link(link: &str) -> bool {
+    ty_lib::is_web_link(link) && link.len() <= 255
+}
+
+/// The manifest of the newest version in `~/.cargo/registry/src`, or rather
+/// the one that sorts last.
+fn cargo_manifest(program: &str) -> Option<PathBuf> {
+    let cargo_home = std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cargo")))?;
+    let prefix = format!("{}-", program);
+
+    let mut crates = Vec::new();
+    for index in fs::read_dir(cargo_home.join("registry").join("src"))
+        .ok()?
+        .flatten()
+    {
+        for entry in fs::read_dir(index.path()).into_iter().flatten().flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // `clap-2.33.3`, but not `clap-verbosity-flag-1.0.0`
+            let is_version = name
+                .strip_prefix(&prefix)
+                .is_some_and(|version| version.starts_with(|c: char| c.is_ascii_digit()));
+            if is_version {
+                crates.push(entry.path());
+            }
+        }
+    }
+    crates.sort();
+    crates.pop().map(|path| path.join("Cargo.toml"))
+}
+
+/// The project's own packages first, then the usual places of global ones.
+fn node_modules(repo: Option<&Path>) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(repo) = repo {
+        dirs.push(repo.join("node_modules"));
+    }
+    if let Some(prefix) = std::env::var_os("NPM_CONFIG_PREFIX") {
+        dirs.push(PathBuf::from(prefix).join("lib").join("node_modules"));
+    }
+    if let Some(home) = dirs::home_dir() {
+        dirs.push(home.join(".npm-global").join("lib").join("node_modules"));
+    }
+    dirs.push(PathBuf::from("/usr/local/lib/node_modules"));
+    dirs.push(PathBuf::from("/usr/lib/node_modules"));
+    dirs
+}
+
+/// Cargo has no field for it, `funding` is looked for in `[package]` and
+/// `[package.metadata]`, next to `repository`.
+fn cargo_toml(content: &str) -> Links {
+    let mut links = Links::default();
+    let mut in_package = false;
+    let mut lines = content.lines();
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_package = line == "[package]" || line == "[package.metadata]";
+            continue;
+        }
+        if !in_package {
+            continue;
+        }
+
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => continue,
+        };
+        match key {
+            "repository" => links.repository = Some(repository_url(value.trim_matches('"'))),
+            "funding" if value.starts_with('[') => {
+                // arrays are spread over several lines in published manifests
+                let mut array = value.to_string();
+                while !array.contains(']') {
+                    match lines.next() {
+                        Some(line) => array.push_str(line),
+                        None => break,
+                    }
+                }
+                links.funding.extend(list(&array));
+            }
+            "funding" => links.funding.push(value.trim_matches('"').to_string()),
+            _ => {}
+        }
+    }
+    links
+}
+
+/// `funding` and `repository` can be strings or objects with an url, funding
+/// also a list of them.
+fn package_json(content: &str) -> Links {
+    fn url(value: &Value) -> Option<String> {
+        value
+            .as_str()
+            .or_else(|| value["url"].as_str())
+            .map(String::from)
+    }
+
+    let package: Value = match serde_json::from_str(content) {
+        Ok(package) => package,
+        Err(_) => return Links::default(),
+    };
+
+    let funding = match package["funding"].as_array() {
+        Some(funding) => funding.iter().filter_map(url).collect(),
+        None => url(&package["funding"]).into_iter().collect(),
+    };
+    Links {
+        funding,
+        repository: url(&package["repository"]).map(|repository| repository_url(&repository)),
+    }
+}
+
+/// `git+https://github.com/a/b.git`, `github:a/b` and `a/b` all become
+/// `https://github.com/a/b`.
+fn repository_url(repository: &str) -> String {
+    let repository = repository.trim_start_matches("git+");
+    let repository = repository.strip_suffix(".git").unwrap_or(repository);
+    if let Some(path) = repository.strip_prefix("github:") {
+        format!("https://github.com/{}", path)
+    } else if let Some(rest) = repository.strip_prefix("git://") {
+        format!("https://{}", rest)
+    } else if !repository.contains(':') && repository.matches('/').count() == 1 {
+        format!("https://github.com/{}", repository)
+    } else {
+        repository.to_string()
+    }
+}
+
+/// Just enough yaml for the format github documents: a platform per line with
+/// one or a list of accounts, `custom` with urls.
+fn funding_yml(content: &str) -> Vec<String> {
+    let mut links = Vec::new();
+    let mut platform = String::new();
+    for line in content.lines() {
+        let line = line.split(" #").next().unwrap_or_default();
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let accounts = if let Some(item) = trimmed.strip_prefix('-') {
+            list(item)
+        } else if let Some((key, value)) = trimmed.split_once(':') {
+            platform = key.trim().to_string();
+            list(value)
+        } else {
+            continue;
+        };
+        links.extend(
+            accounts
+                .iter()
+                .filter_map(|account| platform_url(&platform, account)),
+        );
+    }
+    links
+}
+
+fn platform_url(platform: &str, account: &str) -> Option<String> {
+    let base = match platform {
+        "custom" => return Some(account.to_string()),
+        "github" => "https://github.com/sponsors/",
+        "patreon" => "https://www.patreon.com/",
+        "open_collective" => "https://opencollective.com/",
+        "ko_fi" => "https://ko-fi.com/",
+        "liberapay" => "https://liberapay.com/",
+        "tidelift" => "https://tidelift.com/funding/github/",
+        "polar" => "https://polar.sh/",
+        "buy_me_a_coffee" => "https://www.buymeacoffee.com/",
+        _ => return None,
+    };
+    Some(format!("{}{}", base, account))
+}
+
+/// `"a"`, `[a, "b"]` or `["a", "b",]`, empty values and yaml's null left out.
+fn list(value: &str) -> Vec<String> {
+    value
+        .trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split(',')
+        .map(|item| item.trim().trim_matches(|c| c == '"' || c == '\''))
+        .filter(|item| !item.is_empty() && *item != "~" && *item != "null")
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn manifests_are_read() {
+    let cargo = "[package]\nname = \"ripgrep\"\nrepository = \"https://github.com/BurntSushi/ripgrep\"\n\n[package.metadata]\nfunding = [\n    \"https://github.com/sponsors/BurntSushi\",\n]\n\n[dependencies.regex]\nrepository = \"nope\"\n";
+    assert_eq!(
+        cargo_toml(cargo),
+        Links {
+            funding: vec!["https://github.com/sponsors/BurntSushi".to_string()],
+            repository: Some("https://github.com/BurntSushi/ripgrep".to_string()),
+        }
+    );
+
+    let npm = r#"{"funding":[{"type":"opencollective","url":"https://opencollective.com/babel"},"https://tidelift.com/x"],"repository":{"type":"git","url":"git+https://github.com/babel/babel.git"}}"#;
+    assert_eq!(
+        package_json(npm),
+        Links {
+            funding: vec![
+                "https://opencollective.com/babel".to_string(),
+                "https://tidelift.com/x".to_string()
+            ],
+            repository: Some("https://github.com/babel/babel".to_string()),
+        }
+    );
+    assert_eq!(repository_url("github:a/b"), "https://github.com/a/b");
+
+    let yml = "# comment\ngithub: [octocat, surftocat]\npatreon: octocat # me\nko_fi: ~\ncustom:\n  - \"https://paypal.me/octocat\"\n";
+    assert_eq!(
+        funding_yml(yml),
+        vec![
+            "https://github.com/sponsors/octocat",
+            "https://github.com/sponsors/surftocat",
+            "https://www.patreon.com/octocat",
+            "https://paypal.me/octocat"
+        ]
+    );
+}
@@ -0,0 +1,100 @@
+//! Reading the shell history, to find out which tools are used the most. It
+//! never leaves the machine.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// How far back the shell history is looked at.
+const RECENT_COMMANDS: usize = 1000;
+
+/// The last lines of the shell history, as far as it can be found.
+pub fn recent() -> Vec<String> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    let candidates = env::var_os("HISTFILE")
+        .map(PathBuf::from)
+        .into_iter()
+        .chain(home.iter().flat_map(|home| {
+            vec![
+                home.join(".zsh_history"),
+                home.join(".bash_history"),
+                home.join(".local/share/fish/fish_history"),
+            ]
+        }));
+
+    for path in candidates {
+        // zsh stores some characters metafied, which isn't valid utf-8
+        if let Ok(bytes) = fs::read(&path) {
+            let history = String::from_utf8_lossy(&bytes);
+            let lines: Vec<String> = history.lines().map(String::from).collect();
+            let recent = lines.len().saturating_sub(RECENT_COMMANDS);
+            return lines[recent..].to_vec();
+        }
+    }
+    Vec::new()
+}
+
+/// The most used programs in the history lines, most used first.
+pub fn rank_programs(lines: &[String]) -> Vec<(String, usize)> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for program in lines.iter().filter_map(|line| program_of(line)) {
+        *counts.entry(program).or_default() += 1;
+    }
+
+    let mut ranked: Vec<(String, usize)> = counts
+        .into_iter()
+        .map(|(program, count)| (program.to_string(), count))
+        .collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked
+}
+
+/// The program a history line ran, understands bash, zsh's extended and
+/// fish's history format.
+fn program_of(line: &str) -> Option<&str> {
+    let command = if line.starts_with(": ") {
+        // zsh: `: 1609459200:0;cargo build`
+        line.split_once(';')?.1
+    } else if let Some(command) = line.strip_prefix("- cmd: ") {
+        command
+    } else if line.starts_with("  ") || line.starts_with('#') {
+        // fish's `when:` and `paths:` lines, bash's timestamps
+        return None;
+    } else {
+        line
+    };
+
+    command
+        .split_whitespace()
+        .find(|word| !word.contains('=') && *word != "sudo")
+        .filter(|program| *program != "ty")
+        .map(|program| program.rsplit('/').next().unwrap_or(program))
+}
+
+#[test]
+fn history_is_ranked() {
+    let lines: Vec<String> = vec![
+        "cargo build",
+        ": 1609459200:0;cargo test",
+        "- cmd: git status",
+        "  when: 1609459200",
+        "#1609459200",
+        "RUST_LOG=debug /usr/bin/rg ty",
+        "sudo git push",
+        "ty cargo",
+        "",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect();
+
+    assert_eq!(
+        rank_programs(&lines),
+        vec![
+            ("cargo".to_string(), 2),
+            ("git".to_string(), 2),
+            ("rg".to_string(), 1)
+        ]
+    );
+}
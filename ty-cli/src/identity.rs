@@ -0,0 +1,102 @@
+//! `ty keygen`: a key to sign thank-yous with, so the server can tell they
+//! come from the same person, optionally with a public display name.
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::json;
+use ty_client::{blocking::Client, Key};
+
+use crate::error::Error;
+use crate::ledger;
+use crate::output::Output;
+
+pub struct Options {
+    pub output: Output,
+    /// Public display name to bind to the key.
+    pub name: Option<String>,
+    /// Replace an existing key.
+    pub force: bool,
+}
+
+/// Where the secret key is kept, `TY_KEY` or the data directory.
+pub fn path() -> Option<PathBuf> {
+    env::var_os("TY_KEY")
+        .map(PathBuf::from)
+        .or_else(|| ledger::data_dir().map(|dir| dir.join("key")))
+}
+
+/// The key from `ty keygen`, if there is one. A key that can't be read is only
+/// worth a warning, the thank-you goes out unsigned.
+pub fn load() -> Option<Key> {
+    let path = path()?;
+    let hex = match fs::read_to_string(&path) {
+        Ok(hex) => hex,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return None,
+        Err(err) => {
+            eprintln!("Couldn't read your key from {}: {}", path.display(), err);
+            return None;
+        }
+    };
+    match Key::from_hex(&hex) {
+        Ok(key) => Some(key),
+        Err(err) => {
+            eprintln!("Couldn't read your key from {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+pub fn keygen(client: &Client, options: &Options) -> Result<(), Error> {
+    let path = path().ok_or_else(|| {
+        Error::Aborted("Found no data directory to keep the key in, set TY_KEY.".to_string())
+    })?;
+
+    let key = if !path.exists() || options.force {
+        let key = Key::generate();
+        save(&key, &path)?;
+        key
+    } else if options.name.is_some() {
+        load().ok_or_else(|| {
+            Error::Aborted("Use --force to replace the key that can't be read.".to_string())
+        })?
+    } else {
+        return Err(Error::Aborted(format!(
+            "There is a key at {} already, use --force to replace it.",
+            path.display()
+        )));
+    };
+
+    if let Some(ref name) = options.name {
+        client.set_identity(&key, Some(name.clone()))?;
+    }
+
+    output_key(&key, options);
+    Ok(())
+}
+
+fn save(key: &Key, path: &Path) -> io::Result<()> {
+    ledger::create_dir(path)?;
+    let mut file = ledger::open_private(
+        OpenOptions::new().create(true).write(true).truncate(true),
+        path,
+    )?;
+    writeln!(file, "{}", key.to_hex())
+}
+
+fn output_key(key: &Key, options: &Options) {
+    let output = &options.output;
+    output.json(&json!({
+        "public_key": key.public_key(),
+        "display_name": options.name,
+    }));
+    output.text(&format!("Your public key is {}", key.public_key()));
+    if let Some(ref name) = options.name {
+        output.text(&format!("Display name: {}", name));
+    }
+    output.text(
+        "Your thank-yous are signed with it from now on, use --anonymous to send one without.",
+    );
+}
@@ -0,0 +1,36 @@
+//! `ty inbox`: the replies maintainers left to your signed thank-yous.
+
+use serde_json::json;
+use ty_client::blocking::Client;
+
+use crate::error::Error;
+use crate::identity;
+use crate::output::Output;
+
+pub fn show(client: &Client, output: &Output) -> Result<(), Error> {
+    let key = identity::load().ok_or_else(|| {
+        Error::Aborted(
+            "Replies come to the key your thank-yous are signed with, generate one with ty keygen."
+                .to_string(),
+        )
+    })?;
+
+    let entries = client.inbox(&key)?;
+
+    output.json(&json!({ "entries": entries }));
+    if entries.is_empty() {
+        output.text("No replies yet.");
+    }
+    for entry in &entries {
+        output.text(&format!(
+            "{} #{}: {}",
+            entry.program,
+            entry.id,
+            entry.note.as_deref().unwrap_or("(no note)")
+        ));
+        for reply in &entry.replies {
+            output.text(&format!("  ↪ {}", reply.reply));
+        }
+    }
+    Ok(())
+}
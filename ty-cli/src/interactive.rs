@@ -0,0 +1,156 @@
+//! `ty --interactive`: pick tools from the shell history, write one note for
+//! all of them and send it after a preview.
+
+use console::{style, Key, Term};
+use dialoguer::{theme::ColorfulTheme, Confirm, Input, MultiSelect};
+use ty_client::blocking::Client;
+use ty_lib::{ThankYouMessage, MAX_NOTE_LENGTH};
+
+use crate::error::Error;
+use crate::history;
+use crate::ledger;
+
+/// How many of the most used tools are offered.
+const OFFERED_TOOLS: usize = 15;
+
+pub fn run(client: &Client) -> Result<Vec<ThankYouMessage>, Error> {
+    let term = Term::stderr();
+    let theme = ColorfulTheme::default();
+
+    let programs = pick_programs(&theme)?;
+    let note = write_note(&term, &programs[0])?;
+
+    let messages: Vec<ThankYouMessage> = programs
+        .into_iter()
+        .map(|program| ThankYouMessage {
+            program,
+            note: note.clone(),
+            ..Default::default()
+        })
+        .collect();
+
+    term.write_line(&format!("\n{}", style("Preview").bold()))?;
+    for message in &messages {
+        term.write_line(&format!("  ty {}", message.program))?;
+    }
+    if let Some(ref note) = note {
+        for line in note.lines() {
+            term.write_line(&format!("  > {}", line))?;
+        }
+    }
+
+    let send = Confirm::with_theme(&theme)
+        .with_prompt(format!("Send {} thank you(s)?", messages.len()))
+        .default(true)
+        .interact_on(&term)?;
+    if !send {
+        return Err(Error::Aborted("Aborting, nothing was sent.".to_string()));
+    }
+
+    for message in &messages {
+        let created = crate::send_ty_note(client, message)?;
+        ledger::remember(Some((message, created.as_ref())));
+    }
+    Ok(messages)
+}
+
+fn pick_programs(theme: &ColorfulTheme) -> Result<Vec<String>, Error> {
+    let mut ranked = history::rank_programs(&history::recent());
+    ranked.truncate(OFFERED_TOOLS);
+    if ranked.is_empty() {
+        let program: String = Input::with_theme(theme)
+            .with_prompt("No shell history found, which tool do you want to thank?")
+            .interact_text()?;
+        return Ok(vec![program.trim().to_string()]);
+    }
+
+    let items: Vec<String> = ranked
+        .iter()
+        .map(|(program, count)| format!("{} ({} times)", program, count))
+        .collect();
+    let picked = MultiSelect::with_theme(theme)
+        .with_prompt("Which tools do you want to thank? (space to pick, enter to continue)")
+        .items(&items)
+        .interact()?;
+    if picked.is_empty() {
+        return Err(Error::Aborted("Aborting, no tool was picked.".to_string()));
+    }
+
+    Ok(picked
+        .into_iter()
+        .map(|index| ranked[index].0.clone())
+        .collect())
+}
+
+/// A one line note prompt with a live character count. Enter sends, an empty
+/// note means no note, Escape aborts.
+fn write_note(term: &Term, program: &str) -> Result<Option<String>, Error> {
+    term.write_line("Write a note, or leave it empty (enter to finish, esc to abort):")?;
+
+    let mut note = String::new();
+    let mut problem: Option<String> = None;
+    loop {
+        render_note(term, &note, problem.as_deref())?;
+
+        match term.read_key()? {
+            Key::Enter => {
+                let message = ThankYouMessage {
+                    program: program.to_string(),
+                    note: Some(note.trim().to_string()),
+                    ..Default::default()
+                };
+                match ty_client::validate(&message) {
+                    Ok(()) => break,
+                    Err(ty_client::Error::Validation(errors)) => {
+                        problem = errors.values().flatten().next().cloned();
+                    }
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            Key::Escape | Key::Char('\u{3}') => {
+                term.write_line("")?;
+                return Err(Error::Aborted("Aborting, nothing was sent.".to_string()));
+            }
+            Key::Backspace => {
+                note.pop();
+                problem = None;
+            }
+            Key::Char(c) if !c.is_control() => {
+                note.push(c);
+                problem = None;
+            }
+            _ => {}
+        }
+    }
+    term.write_line("")?;
+
+    let note = note.trim();
+    Ok(if note.is_empty() {
+        None
+    } else {
+        Some(note.to_string())
+    })
+}
+
+/// Redraws the prompt line. Only the end of the note is shown once it's
+/// longer than the terminal is wide, wrapped lines can't be redrawn.
+fn render_note(term: &Term, note: &str, problem: Option<&str>) -> Result<(), Error> {
+    let length = note.chars().count();
+    let counter = format!("[{:>4}/{}] ", length, MAX_NOTE_LENGTH);
+    let problem = problem.map(|p| format!("  {}", p)).unwrap_or_default();
+
+    let (_, width) = term.size();
+    let room = (width as usize).saturating_sub(counter.len() + problem.chars().count() + 1);
+    let visible: String = note.chars().skip(length.saturating_sub(room)).collect();
+
+    let counter = if length as u64 > MAX_NOTE_LENGTH {
+        style(counter).red()
+    } else {
+        style(counter).dim()
+    };
+
+    term.clear_line()?;
+    term.write_str(&format!("{}{}{}", counter, visible, style(problem).red()))?;
+    term.flush()?;
+    Ok(())
+}
@@ -0,0 +1,293 @@
+//! A ledger of the thank-yous sent from this machine, so ty knows what was
+//! thanked already. It's a json lines file in the user's data directory, or
+//! at `$TY_LEDGER`, and it never leaves the machine. It holds the edit tokens
+//! of the notes, so only the user can read it.
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use ty_lib::{NoteCreated, ThankYouMessage};
+
+use crate::error::Error;
+use crate::output::Output;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+    pub program: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub sent_at: DateTime<Utc>,
+    /// The api the thank-you was sent to.
+    #[serde(default)]
+    pub server: String,
+    /// The id of the note on the server, if it told us.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<i64>,
+    /// The secret to edit or retract the note with.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub retracted: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl Entry {
+    /// Whether `ty edit` and `ty retract` can change it, the server decides if
+    /// it's too old.
+    pub fn is_changeable(&self) -> bool {
+        self.server == crate::API_ENDPOINT
+            && self.id.is_some()
+            && self.token.is_some()
+            && !self.retracted
+    }
+}
+
+pub fn path() -> Option<PathBuf> {
+    env::var_os("TY_LEDGER")
+        .map(PathBuf::from)
+        .or_else(|| data_dir().map(|dir| dir.join("ledger.jsonl")))
+}
+
+/// ty's own directory in the user's data directory.
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("ty"))
+}
+
+/// Appends the sent messages to the ledger. The thank-yous are sent already,
+/// so a ledger that can't be written is only worth a warning.
+pub fn remember<'a>(
+    sent: impl IntoIterator<Item = (&'a ThankYouMessage, Option<&'a NoteCreated>)>,
+) {
+    let sent_at = Utc::now();
+    let entries = sent.into_iter().map(|(message, created)| Entry {
+        program: message.program.clone(),
+        note: message.note.clone(),
+        sent_at,
+        server: crate::API_ENDPOINT.to_string(),
+        id: created.map(|created| created.id),
+        token: created.map(|created| created.token.clone()),
+        retracted: false,
+    });
+
+    if let Err(err) = append(entries) {
+        eprintln!("Couldn't write to the ledger of sent thank-yous: {}", err);
+    }
+}
+
+fn append(entries: impl Iterator<Item = Entry>) -> io::Result<()> {
+    let path =
+        path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+    create_dir(&path)?;
+
+    let mut file = open_private(OpenOptions::new().create(true).append(true), &path)?;
+    for entry in entries {
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    }
+    Ok(())
+}
+
+/// Changes the entry of the note with this id on the current server. Like
+/// `remember`, it's too late to fail, so problems are only warned about.
+pub fn amend(id: i64, change: impl FnOnce(&mut Entry)) {
+    if let Err(err) = rewrite(id, change) {
+        eprintln!("Couldn't update the ledger of sent thank-yous: {}", err);
+    }
+}
+
+/// Other lines are kept as they are, even those that can't be read.
+fn rewrite(id: i64, change: impl FnOnce(&mut Entry)) -> io::Result<()> {
+    let path =
+        path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+    let content = fs::read_to_string(&path)?;
+
+    let mut change = Some(change);
+    let mut lines = Vec::new();
+    for line in content.lines() {
+        match serde_json::from_str::<Entry>(line) {
+            Ok(mut entry) if entry.id == Some(id) && entry.server == crate::API_ENDPOINT => {
+                if let Some(change) = change.take() {
+                    change(&mut entry);
+                }
+                lines.push(serde_json::to_string(&entry)?);
+            }
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    let tmp = path.with_extension("jsonl.tmp");
+    let mut file = open_private(
+        OpenOptions::new().create(true).write(true).truncate(true),
+        &tmp,
+    )?;
+    for line in lines {
+        writeln!(file, "{}", line)?;
+    }
+    fs::rename(tmp, path)
+}
+
+/// Creates the directory the file goes in. ty's data directory is only for
+/// the user, even if it was there before, directories picked with `TY_LEDGER`
+/// or `TY_KEY` are left as they are.
+pub fn create_dir(path: &Path) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+        if data_dir().as_deref() == Some(dir) {
+            restrict(dir, 0o700)?;
+        }
+    }
+    Ok(())
+}
+
+/// Opens the file so only the user can read it. Files written before ty took
+/// care of that are tightened too.
+pub fn open_private(options: &mut OpenOptions, path: &Path) -> io::Result<File> {
+    let file = private(options).open(path)?;
+    restrict(path, 0o600)?;
+    Ok(file)
+}
+
+#[cfg(unix)]
+fn private(options: &mut OpenOptions) -> &mut OpenOptions {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.mode(0o600)
+}
+
+#[cfg(not(unix))]
+fn private(options: &mut OpenOptions) -> &mut OpenOptions {
+    options
+}
+
+#[cfg(unix)]
+fn restrict(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn restrict(_path: &Path, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
+
+/// Everything sent so far, oldest first. Lines that can't be read are skipped,
+/// a missing ledger is an empty one.
+pub fn read() -> io::Result<Vec<Entry>> {
+    let content = match path().map(fs::read_to_string) {
+        Some(Ok(content)) => content,
+        Some(Err(err)) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        _ => return Ok(Vec::new()),
+    };
+
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Which entries `ty history` shows. Dates are days in local time, both ends
+/// are included.
+#[derive(Debug, Default)]
+pub struct Filter {
+    pub programs: Vec<String>,
+    pub since: Option<NaiveDate>,
+    pub until: Option<NaiveDate>,
+    /// Only the most recent entries.
+    pub limit: Option<usize>,
+}
+
+impl Filter {
+    fn matches(&self, entry: &Entry) -> bool {
+        let day = entry.sent_at.with_timezone(&Local).naive_local().date();
+        (self.programs.is_empty() || self.programs.contains(&entry.program))
+            && self.since.is_none_or(|since| day >= since)
+            && self.until.is_none_or(|until| day <= until)
+    }
+}
+
+/// `ty history`: what was sent from this machine, oldest first.
+pub fn history(filter: &Filter, output: &Output) -> Result<(), Error> {
+    let mut entries: Vec<Entry> = read()?
+        .into_iter()
+        .filter(|entry| filter.matches(entry))
+        .collect();
+    if let Some(limit) = filter.limit {
+        entries.drain(..entries.len().saturating_sub(limit));
+    }
+
+    // the tokens stay in the ledger, exports tend to get shared
+    for entry in &mut entries {
+        entry.token = None;
+    }
+    output.json(&json!(entries));
+
+    let rows: Vec<Vec<String>> = entries
+        .iter()
+        .map(|entry| {
+            vec![
+                entry
+                    .sent_at
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string(),
+                entry.program.clone(),
+                entry.id.map(|id| id.to_string()).unwrap_or_default(),
+                if entry.retracted {
+                    "(retracted)".to_string()
+                } else {
+                    entry.note.as_deref().map(preview).unwrap_or_default()
+                },
+            ]
+        })
+        .collect();
+    if rows.is_empty() {
+        output.text("Nothing sent yet, or nothing that matches.");
+    } else {
+        output.table(&["sent", "program", "id", "note"], &rows);
+    }
+    Ok(())
+}
+
+/// The first line of the note, shortened to fit a table.
+fn preview(note: &str) -> String {
+    let line = note.lines().next().unwrap_or_default();
+    if line.chars().count() > 40 || note.lines().nth(1).is_some() {
+        format!("{}…", line.chars().take(39).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+#[test]
+fn entries_are_filtered() {
+    let entry: Entry = serde_json::from_str(
+        r#"{"program":"rg","sent_at":"2021-01-15T12:00:00Z","server":"https://ty.example/v0"}"#,
+    )
+    .unwrap();
+    let day = |d: &str| Some(NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap());
+
+    assert!(Filter::default().matches(&entry));
+    assert!(Filter {
+        programs: vec!["rg".to_string()],
+        since: day("2021-01-15"),
+        until: day("2021-01-15"),
+        limit: None,
+    }
+    .matches(&entry));
+    assert!(!Filter {
+        since: day("2021-01-16"),
+        ..Filter::default()
+    }
+    .matches(&entry));
+    assert!(!Filter {
+        programs: vec!["git".to_string()],
+        ..Filter::default()
+    }
+    .matches(&entry));
+}
@@ -1,8 +1,32 @@
 extern crate clap;
-use clap::{App, Arg};
+use chrono::NaiveDate;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use load_dotenv::try_load_dotenv;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
 
-use ty_lib::ThankYouMessage;
+mod change;
+mod deps;
+mod editor;
+mod error;
+mod funding;
+mod history;
+mod identity;
+mod inbox;
+mod interactive;
+mod ledger;
+mod output;
+mod read;
+mod suggest;
+mod table;
+
+use error::Error;
+use output::Output;
+
+use ty_client::blocking::Client;
+use ty_lib::{NoteCreated, ThankYouMessage};
+use uuid::Uuid;
 
 try_load_dotenv!();
 fn main() {
@@ -14,58 +38,457 @@ fn main() {
         .about("Say thank you to the tools (and hopefully it's authors) you use by simply typing ty in your terminal.")
         .arg(Arg::with_name("TOOL")
             .help("Name of the tool you want to thank. If left blank, it takes the last command in the history.")
-            .required(true)
+            .required_unless("interactive")
             .index(1))
         .arg(Arg::with_name("message")
             .short("m")
             .long("message")
             .takes_value(true)
             .multiple(false)
-            .help("Add an optional message to your thank you."))
+            .help("Add an optional message to your thank you. Use - to read it from stdin."))
+        .arg(Arg::with_name("message-file")
+            .short("F")
+            .long("message-file")
+            .value_name("PATH")
+            .takes_value(true)
+            .conflicts_with("message")
+            .help("Read the message from a file."))
+        .arg(Arg::with_name("edit")
+            .short("e")
+            .long("edit")
+            .conflicts_with_all(&["message", "message-file"])
+            .help("Write the message in your $EDITOR. This is the default in a terminal, if no message is given."))
+        .arg(Arg::with_name("no-edit")
+            .long("no-edit")
+            .conflicts_with("edit")
+            .help("Don't open the editor, say thank you without a message."))
+        .arg(Arg::with_name("interactive")
+            .short("i")
+            .long("interactive")
+            .conflicts_with_all(&["TOOL", "message", "message-file", "edit", "no-edit", "private", "share-links", "repo"])
+            .help("Pick tools from your shell history, write a note and preview it before sending."))
+        .arg(Arg::with_name("private")
+            .long("private")
+            .help("Only the maintainers of the tool can read the note."))
+        .arg(Arg::with_name("repo")
+            .long("repo")
+            .value_name("PATH")
+            .takes_value(true)
+            .help("Also look for sponsor links in the .github/FUNDING.yml and node_modules of this repository."))
+        .arg(Arg::with_name("share-links")
+            .long("share-links")
+            .help("Send the sponsor links found on this machine along, to help deliver the thank-you."))
+        .arg(Arg::with_name("anonymous")
+            .long("anonymous")
+            .help("Don't sign the thank-you, even if you generated a key with ty keygen."))
+        .arg(Arg::with_name("output")
+            .short("o")
+            .long("output")
+            .takes_value(true)
+            .possible_values(&["text", "json"])
+            .default_value("text")
+            .global(true)
+            .help("Print the result as text or json."))
+        .arg(Arg::with_name("quiet")
+            .short("q")
+            .long("quiet")
+            .global(true)
+            .help("Only print errors (to stderr)."))
+        .after_help("Exits with 1 if aborted, 2 if the note doesn't pass validation, 3 if the server can't be reached and 4 if the server rejects the request.")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .setting(AppSettings::VersionlessSubcommands)
+        .subcommand(SubCommand::with_name("stats")
+            .about("Shows how often tools were thanked, all of them if none are given.")
+            .arg(Arg::with_name("TOOL")
+                .multiple(true)
+                .help("Names of the tools to look up."))
+            .arg(json_arg())
+            .arg(limit_arg()))
+        .subcommand(SubCommand::with_name("show")
+            .about("Shows how often a tool was thanked and the notes it got.")
+            .arg(Arg::with_name("TOOL")
+                .required(true)
+                .help("Name of the tool to look up."))
+            .arg(json_arg())
+            .arg(limit_arg().help("Show at most this many notes.")))
+        .subcommand(SubCommand::with_name("top")
+            .about("Shows the most thanked tools.")
+            .arg(json_arg())
+            .arg(limit_arg().default_value("10")))
+        .subcommand(SubCommand::with_name("suggest")
+            .about("Suggests the tools you use the most but never thanked, read from your shell history. Only the thank-yous you pick leave your machine.")
+            .arg(json_arg())
+            .arg(limit_arg().default_value("10")))
+        .subcommand(SubCommand::with_name("history")
+            .about("Shows the thank-yous sent from this machine, oldest first.")
+            .arg(Arg::with_name("TOOL")
+                .multiple(true)
+                .help("Only show thank-yous to these tools."))
+            .arg(Arg::with_name("since")
+                .long("since")
+                .value_name("DATE")
+                .takes_value(true)
+                .validator(date_validator)
+                .help("Only show thank-yous sent on this day (YYYY-MM-DD) or later."))
+            .arg(Arg::with_name("until")
+                .long("until")
+                .value_name("DATE")
+                .takes_value(true)
+                .validator(date_validator)
+                .help("Only show thank-yous sent on this day (YYYY-MM-DD) or earlier."))
+            .arg(json_arg())
+            .arg(limit_arg().help("Show only the most recent entries.")))
+        .subcommand(SubCommand::with_name("edit")
+            .about("Changes the note of a thank-you sent from this machine, the last one if no id is given.")
+            .arg(note_id_arg())
+            .arg(Arg::with_name("message")
+                .short("m")
+                .long("message")
+                .takes_value(true)
+                .help("The new note, empty to remove it. Use - to read it from stdin. Opens your $EDITOR if neither this nor --message-file is given."))
+            .arg(Arg::with_name("message-file")
+                .short("F")
+                .long("message-file")
+                .value_name("PATH")
+                .takes_value(true)
+                .conflicts_with("message")
+                .help("Read the new note from a file."))
+            .arg(json_arg()))
+        .subcommand(SubCommand::with_name("retract")
+            .about("Retracts a thank-you sent from this machine, the last one if no id is given.")
+            .arg(note_id_arg())
+            .arg(Arg::with_name("yes")
+                .short("y")
+                .long("yes")
+                .help("Retract without asking first."))
+            .arg(json_arg()))
+        .subcommand(SubCommand::with_name("deps")
+            .about("Thanks every dependency of the project in this directory, read from Cargo.lock, package-lock.json, requirements.txt or go.sum.")
+            .arg(Arg::with_name("message")
+                .short("m")
+                .long("message")
+                .takes_value(true)
+                .help("A note for all of them. Use - to read it from stdin."))
+            .arg(Arg::with_name("yes")
+                .short("y")
+                .long("yes")
+                .help("Send without asking first."))
+            .arg(json_arg()))
+        .subcommand(SubCommand::with_name("inbox")
+            .about("Shows the replies maintainers left to the thank-yous signed with your key.")
+            .arg(json_arg()))
+        .subcommand(SubCommand::with_name("keygen")
+            .about("Generates a key your thank-yous are signed with from then on, so they can be told apart from anonymous ones.")
+            .arg(Arg::with_name("name")
+                .long("name")
+                .takes_value(true)
+                .help("A public display name for the key, sets it for an existing key too."))
+            .arg(Arg::with_name("force")
+                .long("force")
+                .help("Replace the key you have, thank-yous signed with it can't be tied to the new one."))
+            .arg(json_arg()))
         .get_matches();
 
+    let output = Output::from_matches(&matches, matches.subcommand().1);
+
+    let result = match matches.subcommand() {
+        ("stats", Some(sub_matches)) => {
+            let programs: Vec<String> = sub_matches
+                .values_of("TOOL")
+                .map(|tools| tools.map(String::from).collect())
+                .unwrap_or_default();
+            read::stats(&client(), &programs, &read_options(sub_matches, output))
+                .map_err(Error::from)
+        }
+        ("show", Some(sub_matches)) => read::show(
+            &client(),
+            sub_matches.value_of("TOOL").unwrap(),
+            &read_options(sub_matches, output),
+        )
+        .map_err(Error::from),
+        ("top", Some(sub_matches)) => {
+            read::top(&client(), &read_options(sub_matches, output)).map_err(Error::from)
+        }
+        ("suggest", Some(sub_matches)) => {
+            let options = suggest::Options {
+                output,
+                limit: read_options(sub_matches, output)
+                    .limit
+                    .expect("limit has a default"),
+            };
+            suggest::suggest(&client(), &options)
+        }
+        ("history", Some(sub_matches)) => {
+            let date = |name| {
+                sub_matches
+                    .value_of(name)
+                    .map(|date| parse_date(date).expect("validated by clap"))
+            };
+            let filter = ledger::Filter {
+                programs: sub_matches
+                    .values_of("TOOL")
+                    .map(|tools| tools.map(String::from).collect())
+                    .unwrap_or_default(),
+                since: date("since"),
+                until: date("until"),
+                limit: read_options(sub_matches, output).limit,
+            };
+            ledger::history(&filter, &output)
+        }
+        ("edit", Some(sub_matches)) => edit(sub_matches, output),
+        ("retract", Some(sub_matches)) => change::find(note_id(sub_matches)).and_then(|entry| {
+            change::retract(&client(), &entry, sub_matches.is_present("yes"), &output)
+        }),
+        ("deps", Some(sub_matches)) => deps(sub_matches, output),
+        ("inbox", Some(_)) => inbox::show(&client(), &output),
+        ("keygen", Some(sub_matches)) => {
+            let options = identity::Options {
+                output,
+                name: sub_matches.value_of("name").map(String::from),
+                force: sub_matches.is_present("force"),
+            };
+            identity::keygen(&client(), &options)
+        }
+        _ if matches.is_present("interactive") => {
+            let client = signing_client(&matches);
+            interactive::run(&client).map(|sent| {
+                output.json(&serde_json::json!({ "sent": sent }));
+                for message in &sent {
+                    let links = funding::discover(&message.program, None);
+                    mention_sponsoring(&client, &message.program, &links, output);
+                }
+            })
+        }
+        _ => thank(&matches, output),
+    };
+
+    if let Err(err) = result {
+        std::process::exit(output.error(&err));
+    }
+}
+
+fn json_arg() -> Arg<'static, 'static> {
+    Arg::with_name("json")
+        .long("json")
+        .help("Print the result as json, same as --output json.")
+}
+
+fn limit_arg() -> Arg<'static, 'static> {
+    Arg::with_name("limit")
+        .short("n")
+        .long("limit")
+        .takes_value(true)
+        .validator(|limit| {
+            limit
+                .parse::<usize>()
+                .map(|_| ())
+                .map_err(|_| "needs to be a number".to_string())
+        })
+        .help("Show at most this many entries.")
+}
+
+fn note_id_arg() -> Arg<'static, 'static> {
+    Arg::with_name("ID")
+        .validator(|id| {
+            id.parse::<i64>()
+                .map(|_| ())
+                .map_err(|_| "needs to be a number".to_string())
+        })
+        .help("Id of the note, as shown by ty history.")
+}
+
+fn note_id(matches: &ArgMatches) -> Option<i64> {
+    matches
+        .value_of("ID")
+        .map(|id| id.parse().expect("validated by clap"))
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate, chrono::ParseError> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+}
+
+fn date_validator(date: String) -> Result<(), String> {
+    parse_date(&date)
+        .map(|_| ())
+        .map_err(|_| "needs to be a date like 2021-01-31".to_string())
+}
+
+fn read_options(matches: &ArgMatches, output: Output) -> read::Options {
+    read::Options {
+        output,
+        limit: matches
+            .value_of("limit")
+            .map(|limit| limit.parse().expect("validated by clap")),
+    }
+}
+
+const API_ENDPOINT: &str = std::env!(
+    "TY_API_ENDPOINT",
+    "needs TY_API_ENDPOINT in enviroment to compile"
+);
+
+fn client() -> Client {
+    Client::new(API_ENDPOINT)
+}
+
+/// Signs with the key from `ty keygen`, unless asked to stay anonymous.
+fn signing_client(matches: &ArgMatches) -> Client {
+    if matches.is_present("anonymous") {
+        return client();
+    }
+    match identity::load() {
+        Some(key) => client().signed_with(key),
+        None => client(),
+    }
+}
+
+fn thank(matches: &ArgMatches, output: Output) -> Result<(), Error> {
     let program = matches.value_of("TOOL").unwrap().to_string();
 
-    let note = match matches.value_of("message") {
-        None => None,
-        Some(msg) => Some(msg.to_string()),
+    let note = if gives_note(matches) {
+        given_note(matches)?
+    } else if wants_editor(matches) {
+        Some(editor::compose_note(&program, "")?)
+    } else {
+        None
     };
 
-    let message = ThankYouMessage { program, note };
-
-    use validator::Validate;
-    match message.validate() {
-        Ok(()) => send_ty_note(message),
-        Err(e) => {
-            for validation_error_kind in e.errors().values() {
-                use validator::ValidationErrorsKind::Field;
-                match validation_error_kind {
-                    Field(val_errors) => {
-                        for val_error in val_errors {
-                            println!("{}", val_error.message.as_ref().expect("There was an error, but we have no error message for it. Stupid, right!"))
-                        }
-                    }
-                    _ => unimplemented!("Sorry, something unexpected happened!"),
-                }
-            }
+    let links = funding::discover(&program, matches.value_of("repo").map(Path::new));
+    let share = matches.is_present("share-links");
+    let message = ThankYouMessage {
+        program,
+        note,
+        private: matches.is_present("private"),
+        funding: if share {
+            links.funding.clone()
+        } else {
+            Vec::new()
+        },
+        repository: if share {
+            links.repository.clone()
+        } else {
+            None
+        },
+    };
+
+    let client = signing_client(matches);
+    let created = send_ty_note(&client, &message)?;
+    ledger::remember(Some((&message, created.as_ref())));
+    output.json(&serde_json::json!({ "sent": message, "found": links }));
+    mention_sponsoring(&client, &message.program, &links, output);
+    Ok(())
+}
+
+/// Points to where the program can be sponsored, as found on this machine and
+/// if its maintainers told us. It's only a hint, not getting the profile is
+/// fine.
+fn mention_sponsoring(client: &Client, program: &str, links: &funding::Links, output: Output) {
+    if output.quiet || output.is_json() {
+        return;
+    }
+
+    for link in &links.funding {
+        output.text(&format!("Found a sponsor link for {}: {}", program, link));
+    }
+    if let Some(ref repository) = links.repository {
+        output.text(&format!("Its repository: {}", repository));
+    }
+
+    if let Ok(tool) = client.tool(program) {
+        if let Some(link) = tool
+            .profile
+            .funding
+            .first()
+            .filter(|link| !links.funding.contains(link))
+        {
+            output.text(&format!(
+                "Want to go further? Sponsor {} at {}",
+                program, link
+            ));
         }
     }
 }
 
-fn send_ty_note(message: ThankYouMessage) {
-    let endpoint = std::env!(
-        "TY_API_ENDPOINT",
-        "needs TY_API_ENDPOINT in enviroment to compile"
-    )
-    .to_string();
+fn edit(matches: &ArgMatches, output: Output) -> Result<(), Error> {
+    let entry = change::find(note_id(matches))?;
+
+    let note = if gives_note(matches) {
+        given_note(matches)?.filter(|note| !note.trim().is_empty())
+    } else {
+        let current = entry.note.as_deref().unwrap_or_default();
+        Some(editor::compose_note(&entry.program, current)?)
+    };
+
+    change::edit(&client(), &entry, note, &output)
+}
+
+fn deps(matches: &ArgMatches, output: Output) -> Result<(), Error> {
+    let note = given_note(matches)?;
+
+    let options = deps::Options {
+        output,
+        note,
+        yes: matches.is_present("yes"),
+    };
+    deps::thank(&client(), &std::env::current_dir()?, &options)
+}
+
+fn gives_note(matches: &ArgMatches) -> bool {
+    matches.is_present("message") || matches.is_present("message-file")
+}
+
+/// The note from `--message`, stdin with `-m -`, or `--message-file`.
+fn given_note(matches: &ArgMatches) -> Result<Option<String>, Error> {
+    match (
+        matches.value_of("message"),
+        matches.value_of("message-file"),
+    ) {
+        (Some("-"), _) => read_note(io::stdin()),
+        (Some(msg), _) => Ok(Some(msg.to_string())),
+        (None, Some(path)) => read_note(File::open(path)?),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Reads a piped or saved note, surrounding whitespace is dropped and an empty
+/// note is no note.
+fn read_note(mut input: impl Read) -> Result<Option<String>, Error> {
+    let mut note = String::new();
+    input.read_to_string(&mut note)?;
 
-    let response = reqwest::blocking::Client::new()
-        .post(&(endpoint + "/note"))
-        .timeout(core::time::Duration::new(7, 0)) // no one has time to wait
-        .json(&message)
-        .send();
+    let note = note.trim();
+    if note.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(note.to_string()))
+    }
+}
+
+/// Like git, the editor opens when no message was given and we're in a terminal.
+fn wants_editor(matches: &ArgMatches) -> bool {
+    matches.is_present("edit")
+        || (!matches.is_present("no-edit")
+            && atty::is(atty::Stream::Stdin)
+            && atty::is(atty::Stream::Stdout))
+}
 
-    if response.is_err() || response.unwrap().status() != reqwest::StatusCode::CREATED {
-        println!("Faild to collect your thank you note. Please try again later.")
+/// How often sending a thank-you is tried before giving up on the network.
+const SEND_ATTEMPTS: u32 = 3;
+
+/// Sends the message with an idempotency key and retries on network errors. A
+/// request that timed out might have been stored, the key keeps the retry from
+/// counting it twice.
+fn send_ty_note(client: &Client, message: &ThankYouMessage) -> Result<Option<NoteCreated>, Error> {
+    let key = Uuid::new_v4().to_string();
+
+    let mut attempt = 1;
+    loop {
+        match client.post_note_with_key(message, &key) {
+            Err(ty_client::Error::Network(_)) if attempt < SEND_ATTEMPTS => {
+                attempt += 1;
+                std::thread::sleep(std::time::Duration::from_secs(1));
+            }
+            res => return Ok(res?),
+        }
     }
 }
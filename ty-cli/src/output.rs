@@ -0,0 +1,117 @@
+//! How ty talks back: text or json, and exit codes scripts can rely on.
+
+use clap::ArgMatches;
+use serde_json::{json, Value};
+
+use crate::error::Error;
+use crate::table;
+
+/// Anything else: usage errors, the user backing out or problems on this machine.
+pub const EXIT_FAILURE: i32 = 1;
+/// The note didn't pass validation, locally or on the server.
+pub const EXIT_VALIDATION: i32 = 2;
+/// The server couldn't be reached.
+pub const EXIT_NETWORK: i32 = 3;
+/// The server was reached, but didn't accept the request or gave a broken answer.
+pub const EXIT_SERVER: i32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Output {
+    pub format: Format,
+    pub quiet: bool,
+}
+
+impl Output {
+    /// Reads `--output`, `--json` and `--quiet` from the matches of the app and,
+    /// if one was used, of the subcommand.
+    pub fn from_matches(matches: &ArgMatches, sub_matches: Option<&ArgMatches>) -> Self {
+        let all = || std::iter::once(matches).chain(sub_matches);
+
+        let json = all().any(|m| m.is_present("json") || m.value_of("output") == Some("json"));
+        Output {
+            format: if json { Format::Json } else { Format::Text },
+            quiet: all().any(|m| m.is_present("quiet")),
+        }
+    }
+
+    pub fn is_json(&self) -> bool {
+        self.format == Format::Json
+    }
+
+    pub fn text(&self, text: &str) {
+        if !self.quiet && !self.is_json() {
+            println!("{}", text);
+        }
+    }
+
+    pub fn json(&self, value: &Value) {
+        if !self.quiet && self.is_json() {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(value).expect("json values can be serialized")
+            );
+        }
+    }
+
+    pub fn table(&self, header: &[&str], rows: &[Vec<String>]) {
+        if !self.quiet && !self.is_json() {
+            table::print_table(header, rows);
+        }
+    }
+
+    /// Prints the error to stderr, even when quiet, and returns the exit code for it.
+    pub fn error(&self, err: &Error) -> i32 {
+        if self.is_json() {
+            let value = match err {
+                Error::Client(ty_client::Error::Validation(fields)) => json!({
+                    "error": "validation",
+                    "message": err.to_string(),
+                    "fields": fields,
+                }),
+                Error::Client(ty_client::Error::Server(status)) => json!({
+                    "error": "server",
+                    "message": err.to_string(),
+                    "status": status.as_u16(),
+                }),
+                Error::Client(ty_client::Error::Network(_)) => {
+                    json!({ "error": "network", "message": err.to_string() })
+                }
+                Error::Client(ty_client::Error::Decode(_)) => {
+                    json!({ "error": "server", "message": err.to_string() })
+                }
+                Error::Client(ty_client::Error::Refused(_)) => {
+                    json!({ "error": "refused", "message": err.to_string() })
+                }
+                Error::Aborted(_) => json!({ "error": "aborted", "message": err.to_string() }),
+                Error::Io(_) => json!({ "error": "io", "message": err.to_string() }),
+            };
+            eprintln!(
+                "{}",
+                serde_json::to_string_pretty(&value).expect("json values can be serialized")
+            );
+        } else {
+            eprintln!("{}", err);
+        }
+
+        exit_code(err)
+    }
+}
+
+pub fn exit_code(err: &Error) -> i32 {
+    use ty_client::Error::*;
+
+    match err {
+        Error::Client(Validation(_)) => EXIT_VALIDATION,
+        Error::Client(Network(_)) => EXIT_NETWORK,
+        Error::Client(Server(_)) | Error::Client(Decode(_)) | Error::Client(Refused(_)) => {
+            EXIT_SERVER
+        }
+        Error::Aborted(_) | Error::Io(_) => EXIT_FAILURE,
+    }
+}
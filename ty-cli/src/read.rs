@@ -0,0 +1,106 @@
+//! Commands to look at what has been collected so far: `ty stats`, `ty show`
+//! and `ty top`.
+
+use serde_json::json;
+use ty_client::{blocking::Client, Error};
+use ty_lib::ThankYouStats;
+
+use crate::output::Output;
+
+pub struct Options {
+    pub output: Output,
+    pub limit: Option<usize>,
+}
+
+/// The most thanked programs.
+pub fn top(client: &Client, options: &Options) -> Result<(), Error> {
+    let mut stats = client.stats()?;
+    if let Some(limit) = options.limit {
+        stats.truncate(limit);
+    }
+
+    options.output.json(&json!(stats));
+    print_stats(&options.output, &stats);
+    Ok(())
+}
+
+/// How often the given programs were thanked, or all of them if none are given.
+pub fn stats(client: &Client, programs: &[String], options: &Options) -> Result<(), Error> {
+    if programs.is_empty() {
+        return top(client, options);
+    }
+
+    let mut counts = Vec::new();
+    for program in programs {
+        counts.push((program, client.count(program)?));
+    }
+    if let Some(limit) = options.limit {
+        counts.truncate(limit);
+    }
+
+    let json: Vec<_> = counts
+        .iter()
+        .map(|(program, count)| json!({ "program": program, "count": count }))
+        .collect();
+    options.output.json(&json!(json));
+
+    let rows: Vec<Vec<String>> = counts
+        .iter()
+        .map(|(program, count)| vec![program.to_string(), count.to_string()])
+        .collect();
+    options.output.table(&["program", "tys"], &rows);
+    Ok(())
+}
+
+/// The thank-you count and the notes of one program, with the maintainers'
+/// replies.
+pub fn show(client: &Client, program: &str, options: &Options) -> Result<(), Error> {
+    let count = client.count(program)?;
+    let mut detail = client.detail(program)?;
+    if let Some(limit) = options.limit {
+        detail.notes.truncate(limit);
+        detail.entries.truncate(limit);
+    }
+
+    options.output.json(&json!({
+        "program": detail.program,
+        "count": count,
+        "notes": detail.notes,
+        "entries": detail.entries,
+    }));
+
+    options.output.table(
+        &["program", "tys", "notes"],
+        &[vec![
+            detail.program.clone(),
+            count.to_string(),
+            detail.notes.len().to_string(),
+        ]],
+    );
+    // servers before the replies only answer with the notes
+    for (i, note) in detail.notes.iter().enumerate() {
+        options.output.text(&format!("\n{}", note));
+        for reply in detail
+            .entries
+            .get(i)
+            .map_or(&[][..], |entry| &entry.replies)
+        {
+            options.output.text(&format!("  ↪ {}", reply.reply));
+        }
+    }
+    Ok(())
+}
+
+fn print_stats(output: &Output, stats: &[ThankYouStats]) {
+    let rows: Vec<Vec<String>> = stats
+        .iter()
+        .map(|stats| {
+            vec![
+                stats.program.clone(),
+                stats.count.to_string(),
+                stats.note_count.to_string(),
+            ]
+        })
+        .collect();
+    output.table(&["program", "tys", "notes"], &rows);
+}
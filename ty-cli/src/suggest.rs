@@ -0,0 +1,89 @@
+//! `ty suggest`: the most used tools from the shell history that were never
+//! thanked from this machine. Both are only read locally, just the picked
+//! thank-yous are sent.
+
+use std::collections::HashSet;
+
+use dialoguer::{theme::ColorfulTheme, Input, MultiSelect};
+use serde_json::json;
+use ty_client::blocking::Client;
+use ty_lib::ThankYouMessage;
+
+use crate::error::Error;
+use crate::output::Output;
+use crate::{deps, history, ledger};
+
+pub struct Options {
+    pub output: Output,
+    pub limit: usize,
+}
+
+pub fn suggest(client: &Client, options: &Options) -> Result<(), Error> {
+    let thanked: HashSet<String> = ledger::read()?
+        .into_iter()
+        .map(|entry| entry.program)
+        .collect();
+
+    let mut suggestions: Vec<(String, usize)> = history::rank_programs(&history::recent())
+        .into_iter()
+        .filter(|(program, _)| !thanked.contains(program))
+        .collect();
+    suggestions.truncate(options.limit);
+
+    if suggestions.is_empty() {
+        options.output.json(&json!([]));
+        options
+            .output
+            .text("Nothing to suggest, you have thanked your most used tools already.");
+        return Ok(());
+    }
+
+    // scripts get the list, people get asked
+    let interactive = !options.output.is_json()
+        && !options.output.quiet
+        && atty::is(atty::Stream::Stdin)
+        && atty::is(atty::Stream::Stdout);
+    if !interactive {
+        let json: Vec<_> = suggestions
+            .iter()
+            .map(|(program, uses)| json!({ "program": program, "uses": uses }))
+            .collect();
+        options.output.json(&json!(json));
+
+        let rows: Vec<Vec<String>> = suggestions
+            .iter()
+            .map(|(program, uses)| vec![program.to_string(), uses.to_string()])
+            .collect();
+        options.output.table(&["program", "uses"], &rows);
+        return Ok(());
+    }
+
+    let theme = ColorfulTheme::default();
+    let items: Vec<String> = suggestions
+        .iter()
+        .map(|(program, uses)| format!("{} (used {} times, never thanked)", program, uses))
+        .collect();
+    let picked = MultiSelect::with_theme(&theme)
+        .with_prompt("Which tools do you want to thank? (space to pick, enter to continue)")
+        .items(&items)
+        .interact()?;
+    if picked.is_empty() {
+        return Err(Error::Aborted("Aborting, no tool was picked.".to_string()));
+    }
+
+    let note: String = Input::with_theme(&theme)
+        .with_prompt("A note for them (optional)")
+        .allow_empty(true)
+        .interact_text()?;
+    let note = Some(note.trim().to_string()).filter(|note| !note.is_empty());
+
+    let messages: Vec<ThankYouMessage> = picked
+        .into_iter()
+        .map(|index| ThankYouMessage {
+            program: suggestions[index].0.clone(),
+            note: note.clone(),
+            ..Default::default()
+        })
+        .collect();
+    deps::send(client, &messages, &options.output)
+}
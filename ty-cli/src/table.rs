@@ -0,0 +1,55 @@
+/// Prints rows as a table with aligned columns. Columns of numbers are aligned
+/// to the right, all others to the left.
+pub fn print_table(header: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = header.iter().map(|title| title.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let numeric: Vec<bool> = (0..header.len())
+        .map(|column| {
+            rows.iter()
+                .filter_map(|row| row.get(column))
+                .all(|cell| cell.is_empty() || cell.parse::<f64>().is_ok())
+        })
+        .collect();
+
+    let header: Vec<String> = header.iter().map(|title| title.to_string()).collect();
+    println!("{}", format_row(&header, &widths, &numeric));
+    for row in rows {
+        println!("{}", format_row(row, &widths, &numeric));
+    }
+}
+
+fn format_row(row: &[String], widths: &[usize], numeric: &[bool]) -> String {
+    row.iter()
+        .zip(widths)
+        .zip(numeric)
+        .map(|((cell, width), numeric)| {
+            if *numeric {
+                format!("{:>width$}", cell, width = width)
+            } else {
+                format!("{:<width$}", cell, width = width)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("  ")
+        .trim_end()
+        .to_string()
+}
+
+#[test]
+fn rows_are_aligned() {
+    let widths = [6, 4];
+    let numeric = [false, true];
+    assert_eq!(
+        format_row(&["rustc".to_string(), "12".to_string()], &widths, &numeric),
+        "rustc     12"
+    );
+    assert_eq!(
+        format_row(&["🦀".to_string(), "1234".to_string()], &widths, &numeric),
+        "🦀       1234"
+    );
+}
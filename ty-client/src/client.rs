@@ -0,0 +1,217 @@
+//! Async transport. On wasm32 reqwest sends the requests with the browser's fetch.
+
+use reqwest::StatusCode;
+#[cfg(feature = "signing")]
+use ty_lib::{Identity, InboxEntry};
+use ty_lib::{
+    NoteCreated, NoteEdit, ThankYouDetail, ThankYouMessage, ThankYouStats, Tool,
+    IDEMPOTENCY_KEY_HEADER,
+};
+
+#[cfg(feature = "signing")]
+use crate::signed_response;
+use crate::{
+    change_response, count_response, json_response, note_response, notes_response,
+    smaller_batch_size, validate, Endpoint, Error, NoteResult, BATCH_SIZE,
+};
+
+#[derive(Debug, Clone)]
+pub struct Client {
+    endpoint: Endpoint,
+    http: reqwest::Client,
+    #[cfg(feature = "signing")]
+    key: Option<crate::Key>,
+}
+
+impl Client {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        let http = reqwest::Client::builder()
+            .timeout(crate::TIMEOUT)
+            .build()
+            .expect("Could not build http client.");
+        #[cfg(target_arch = "wasm32")]
+        let http = reqwest::Client::new();
+
+        Client {
+            endpoint: Endpoint::new(base_url),
+            http,
+            #[cfg(feature = "signing")]
+            key: None,
+        }
+    }
+
+    /// Signs the thank-yous sent with `post_note`, batches stay unsigned.
+    #[cfg(feature = "signing")]
+    pub fn signed_with(mut self, key: crate::Key) -> Self {
+        self.key = Some(key);
+        self
+    }
+
+    pub async fn post_note(&self, message: &ThankYouMessage) -> Result<Option<NoteCreated>, Error> {
+        self.send_note(message, None).await
+    }
+
+    /// Like `post_note`, sending it again with the same key, e.g. after a
+    /// timeout, doesn't count the thank-you twice.
+    pub async fn post_note_with_key(
+        &self,
+        message: &ThankYouMessage,
+        idempotency_key: &str,
+    ) -> Result<Option<NoteCreated>, Error> {
+        self.send_note(message, Some(idempotency_key)).await
+    }
+
+    async fn send_note(
+        &self,
+        message: &ThankYouMessage,
+        idempotency_key: Option<&str>,
+    ) -> Result<Option<NoteCreated>, Error> {
+        validate(message)?;
+        // the server wants a key with signed thank-yous, so a replay is counted once
+        #[cfg(feature = "signing")]
+        let generated = match (idempotency_key, &self.key) {
+            (None, Some(_)) => Some(crate::identity::idempotency_key()),
+            _ => None,
+        };
+        #[cfg(feature = "signing")]
+        let idempotency_key = idempotency_key.or(generated.as_deref());
+
+        let mut request = self.http.post(&self.endpoint.note()).json(message);
+        if let Some(key) = idempotency_key {
+            request = request.header(IDEMPOTENCY_KEY_HEADER, key);
+        }
+        #[cfg(feature = "signing")]
+        if let Some(ref key) = self.key {
+            let now = crate::identity::now();
+            for (name, value) in key.signature_headers(now, idempotency_key, message) {
+                request = request.header(name, value);
+            }
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        note_response(status, &response.text().await?)
+    }
+
+    /// Sends the messages in batches of `BATCH_SIZE`, or what the server takes,
+    /// and returns a result per message, in order. Each batch is stored all or
+    /// nothing, but an error can leave earlier batches stored.
+    pub async fn post_notes(&self, messages: &[ThankYouMessage]) -> Result<Vec<NoteResult>, Error> {
+        let mut results = Vec::with_capacity(messages.len());
+        let mut batch_size = BATCH_SIZE;
+        let mut rest = messages;
+        while !rest.is_empty() {
+            let batch = &rest[..batch_size.min(rest.len())];
+            let response = self
+                .http
+                .post(&self.endpoint.notes())
+                .json(batch)
+                .send()
+                .await?;
+            let status = response.status();
+            let body = response.text().await?;
+
+            if let Some(max) = smaller_batch_size(status, &body, batch.len()) {
+                batch_size = max;
+                continue;
+            }
+            results.extend(notes_response(status, &body)?);
+            rest = &rest[batch.len()..];
+        }
+        Ok(results)
+    }
+
+    /// Replaces the note of a thank-you sent earlier, with the token it was
+    /// created with.
+    pub async fn edit_note(&self, id: i64, token: &str, edit: &NoteEdit) -> Result<(), Error> {
+        validate(edit)?;
+        let response = self
+            .http
+            .patch(&self.endpoint.note_id(id))
+            .bearer_auth(token)
+            .json(edit)
+            .send()
+            .await?;
+        let status = response.status();
+        change_response(status, &response.text().await?)
+    }
+
+    /// Sets the public display name of the key, `None` removes it.
+    #[cfg(feature = "signing")]
+    pub async fn set_identity(
+        &self,
+        key: &crate::Key,
+        display_name: Option<String>,
+    ) -> Result<(), Error> {
+        let identity = Identity {
+            public_key: key.public_key(),
+            display_name,
+        };
+        validate(&identity)?;
+        let mut request = self.http.put(&self.endpoint.identity()).json(&identity);
+        for (name, value) in key.signature_headers(crate::identity::now(), None, &identity) {
+            request = request.header(name, value);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        change_response(status, &response.text().await?)
+    }
+
+    /// The replies maintainers left to the thank-yous signed with the key.
+    #[cfg(feature = "signing")]
+    pub async fn inbox(&self, key: &crate::Key) -> Result<Vec<InboxEntry>, Error> {
+        let request = key.inbox_request();
+        let mut http_request = self.http.post(&self.endpoint.inbox()).json(&request);
+        for (name, value) in key.signature_headers(request.timestamp, None, &request) {
+            http_request = http_request.header(name, value);
+        }
+        let response = http_request.send().await?;
+        let status = response.status();
+        signed_response(status, &response.text().await?)
+    }
+
+    /// Retracts a thank-you sent earlier, with the token it was created with.
+    pub async fn retract_note(&self, id: i64, token: &str) -> Result<(), Error> {
+        let response = self
+            .http
+            .delete(&self.endpoint.note_id(id))
+            .bearer_auth(token)
+            .send()
+            .await?;
+        let status = response.status();
+        change_response(status, &response.text().await?)
+    }
+
+    pub async fn stats(&self) -> Result<Vec<ThankYouStats>, Error> {
+        let (status, body) = self.get(&self.endpoint.stats()).await?;
+        json_response(status, &body)
+    }
+
+    pub async fn count(&self, program: &str) -> Result<i64, Error> {
+        let (status, body) = self.get(&self.endpoint.count(program)).await?;
+        count_response(status, &body)
+    }
+
+    pub async fn detail(&self, program: &str) -> Result<ThankYouDetail, Error> {
+        let (status, body) = self.get(&self.endpoint.detail(program)).await?;
+        json_response(status, &body)
+    }
+
+    /// The count together with what the maintainers tell about the program.
+    pub async fn tool(&self, program: &str) -> Result<Tool, Error> {
+        let response = self
+            .http
+            .get(&self.endpoint.count(program))
+            .header("accept", "application/json")
+            .send()
+            .await?;
+        let status = response.status();
+        json_response(status, &response.text().await?)
+    }
+
+    async fn get(&self, url: &str) -> Result<(StatusCode, String), Error> {
+        let response = self.http.get(url).send().await?;
+        let status = response.status();
+        Ok((status, response.text().await?))
+    }
+}
@@ -0,0 +1,113 @@
+//! Keys to sign thank-yous with, so the server can tell they come from the
+//! same person. Only built with the `signing` feature.
+
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ed25519_dalek::{Keypair, Signer};
+use serde::Serialize;
+use ty_lib::{signed_bytes, InboxRequest, PUBLIC_KEY_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+
+use crate::Error;
+
+/// An ed25519 keypair, its secret half never leaves this process except
+/// through `to_hex`.
+pub struct Key(Keypair);
+
+impl Key {
+    pub fn generate() -> Self {
+        Key(Keypair::generate(&mut rand::rngs::OsRng))
+    }
+
+    /// Reads a key written by `to_hex`.
+    pub fn from_hex(hex: &str) -> Result<Self, Error> {
+        let bytes = hex::decode(hex.trim()).map_err(|err| Error::Decode(err.to_string()))?;
+        Keypair::from_bytes(&bytes)
+            .map(Key)
+            .map_err(|err| Error::Decode(err.to_string()))
+    }
+
+    /// The secret and the public key, hex encoded. Keep it secret.
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.0.to_bytes())
+    }
+
+    /// The hex encoded public key, as sent in the `Ty-Public-Key` header.
+    pub fn public_key(&self) -> String {
+        hex::encode(self.0.public.as_bytes())
+    }
+
+    /// The hex encoded signature, as sent in the `Ty-Signature` header.
+    pub fn sign(&self, bytes: &[u8]) -> String {
+        hex::encode(self.0.sign(bytes).to_bytes())
+    }
+
+    /// The headers that sign a request, made at `timestamp`. The server only
+    /// takes them for a few minutes.
+    pub(crate) fn signature_headers<T: Serialize>(
+        &self,
+        timestamp: i64,
+        idempotency_key: Option<&str>,
+        message: &T,
+    ) -> [(&'static str, String); 3] {
+        [
+            (PUBLIC_KEY_HEADER, self.public_key()),
+            (
+                SIGNATURE_HEADER,
+                self.sign(&signed_bytes(timestamp, idempotency_key, message)),
+            ),
+            (TIMESTAMP_HEADER, timestamp.to_string()),
+        ]
+    }
+
+    /// Asks for the replies to the thank-yous signed with this key.
+    pub(crate) fn inbox_request(&self) -> InboxRequest {
+        InboxRequest {
+            public_key: self.public_key(),
+            timestamp: now(),
+        }
+    }
+}
+
+/// Seconds since the unix epoch, what signatures are timestamped with.
+pub(crate) fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("the clock is after 1970")
+        .as_secs() as i64
+}
+
+/// Signed thank-yous need an `Idempotency-Key`, this one is used if the
+/// caller didn't pick one.
+pub(crate) fn idempotency_key() -> String {
+    hex::encode(rand::random::<[u8; 16]>())
+}
+
+impl Clone for Key {
+    fn clone(&self) -> Self {
+        Key(Keypair::from_bytes(&self.0.to_bytes()).expect("a keypair reads its own bytes"))
+    }
+}
+
+/// Only shows the public key.
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Key").field(&self.public_key()).finish()
+    }
+}
+
+#[test]
+fn keys_are_read_back() {
+    use ed25519_dalek::{Signature, Verifier};
+    use std::convert::TryFrom;
+
+    let key = Key::generate();
+    let read = Key::from_hex(&key.to_hex()).unwrap();
+    assert_eq!(read.public_key(), key.public_key());
+    assert_eq!(read.sign(b"ty"), key.sign(b"ty"));
+    assert_eq!(key.public_key().len(), 64);
+    assert!(!format!("{:?}", key).contains(&key.to_hex()));
+
+    let signature = Signature::try_from(&hex::decode(key.sign(b"ty")).unwrap()[..]).unwrap();
+    assert!(key.0.public.verify(b"ty", &signature).is_ok());
+}
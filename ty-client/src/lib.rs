@@ -0,0 +1,394 @@
+//! Client for the v0 api of ty-server, shared by ty-cli and ty-spa.
+//!
+//! The protocol (urls, validation, how responses are read) lives in this file,
+//! the transports only move bytes. Enable `blocking` for reqwest's blocking
+//! client, `async` for the async one and `wasm` to use the async client from
+//! the browser. `signing` adds `Key`, to sign thank-yous with.
+
+// without a transport, only the url building and validation is of any use
+#![cfg_attr(not(any(feature = "blocking", feature = "async")), allow(dead_code))]
+
+use std::collections::HashMap;
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use serde::{de::DeserializeOwned, Deserialize};
+use ty_lib::NoteCreated;
+#[cfg(test)]
+use ty_lib::ThankYouMessage;
+use urlencoding::encode;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "async")]
+mod client;
+#[cfg(feature = "signing")]
+mod identity;
+
+#[cfg(feature = "async")]
+pub use client::Client;
+#[cfg(feature = "signing")]
+pub use identity::Key;
+
+/// No one has time to wait.
+#[cfg(not(target_arch = "wasm32"))]
+pub const TIMEOUT: Duration = Duration::from_secs(7);
+
+/// Validation messages per field of a `ThankYouMessage`.
+pub type FieldErrors = HashMap<String, Vec<String>>;
+
+/// `post_notes` sends at most this many messages per request, the server's
+/// default `MAX_BATCH_SIZE`. Servers configured with less are told apart by
+/// their answer.
+pub const BATCH_SIZE: usize = 100;
+
+/// What became of one of the messages sent with `post_notes`.
+#[derive(Debug, PartialEq)]
+pub enum NoteResult {
+    /// With the id of the note, if the server tells it.
+    Created(Option<NoteCreated>),
+    Invalid(FieldErrors),
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// The message didn't pass validation, either before sending or on the server.
+    Validation(FieldErrors),
+    /// The server answered, but not with what we asked for.
+    Server(StatusCode),
+    /// The server couldn't be reached or the connection broke.
+    Network(reqwest::Error),
+    /// The response body wasn't what the v0 api promises.
+    Decode(String),
+    /// The server refused the request, with its reason, e.g. a wrong token.
+    Refused(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Validation(errors) => {
+                let mut messages: Vec<&String> = errors.values().flatten().collect();
+                messages.sort();
+                for (i, message) in messages.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}", message)?;
+                }
+                Ok(())
+            }
+            Error::Server(status) => write!(f, "The server answered with {}", status),
+            Error::Network(err) => write!(f, "Couldn't reach the server: {}", err),
+            Error::Decode(err) => write!(f, "Couldn't read the server's answer: {}", err),
+            Error::Refused(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Network(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Network(err)
+    }
+}
+
+/// Urls of the api, relative to its v0 base url, e.g. `https://ty.paulweissenbach.com/v0`.
+#[derive(Debug, Clone)]
+pub struct Endpoint {
+    base: String,
+}
+
+impl Endpoint {
+    pub fn new(base: impl Into<String>) -> Self {
+        let base = base.into();
+        Endpoint {
+            base: base.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn note(&self) -> String {
+        format!("{}/note", self.base)
+    }
+
+    pub fn note_id(&self, id: i64) -> String {
+        format!("{}/note/{}", self.base, id)
+    }
+
+    pub fn identity(&self) -> String {
+        format!("{}/identity", self.base)
+    }
+
+    pub fn inbox(&self) -> String {
+        format!("{}/inbox", self.base)
+    }
+
+    pub fn notes(&self) -> String {
+        format!("{}/notes", self.base)
+    }
+
+    pub fn stats(&self) -> String {
+        self.base.clone()
+    }
+
+    pub fn count(&self, program: &str) -> String {
+        format!("{}/tool/{}", self.base, encode(program))
+    }
+
+    pub fn detail(&self, program: &str) -> String {
+        format!("{}/tool/{}/detail", self.base, encode(program))
+    }
+}
+
+/// The messages of the validation errors, per field.
+pub fn field_messages(errors: &ValidationErrors) -> FieldErrors {
+    errors
+        .field_errors()
+        .into_iter()
+        .map(|(field, errors)| (field.to_string(), messages(errors)))
+        .collect()
+}
+
+fn messages(errors: &[ValidationError]) -> Vec<String> {
+    errors.iter().map(message).collect()
+}
+
+/// Length errors get the actual length appended. It's counted in characters,
+/// not bytes, so it can be hard to guess for notes with emojis.
+fn message(error: &ValidationError) -> String {
+    let message = match error.message {
+        Some(ref message) => message.to_string(),
+        None => error.code.to_string(),
+    };
+
+    match error.params.get("value").and_then(|value| value.as_str()) {
+        Some(value) if error.code == "length" => {
+            format!("{} (it has {} characters)", message, value.chars().count())
+        }
+        _ => message,
+    }
+}
+
+/// Checks the message or edit with the same rules the server uses.
+pub fn validate(message: &impl Validate) -> Result<(), Error> {
+    message
+        .validate()
+        .map_err(|errors| Error::Validation(field_messages(&errors)))
+}
+
+/// Servers before the note ids were introduced answer with an empty string.
+fn note_response(status: StatusCode, body: &str) -> Result<Option<NoteCreated>, Error> {
+    match status {
+        StatusCode::CREATED => Ok(serde_json::from_str(body).ok()),
+        StatusCode::BAD_REQUEST => {
+            match serde_json::from_str::<HashMap<String, Vec<ValidationError>>>(body) {
+                Ok(errors) => Err(Error::Validation(
+                    errors
+                        .into_iter()
+                        .map(|(field, errors)| (field, messages(&errors)))
+                        .collect(),
+                )),
+                Err(_) => Err(refusal(status, body)),
+            }
+        }
+        StatusCode::UNPROCESSABLE_ENTITY => Err(refusal(status, body)),
+        _ => Err(Error::Server(status)),
+    }
+}
+
+/// The answer to editing or retracting a note.
+fn change_response(status: StatusCode, body: &str) -> Result<(), Error> {
+    match status {
+        StatusCode::NO_CONTENT => Ok(()),
+        StatusCode::BAD_REQUEST => note_response(status, body).map(|_| ()),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::NOT_FOUND => {
+            Err(refusal(status, body))
+        }
+        _ => Err(Error::Server(status)),
+    }
+}
+
+/// The server's reason to refuse a request, e.g. a wrong token.
+fn refusal(status: StatusCode, body: &str) -> Error {
+    #[derive(Deserialize)]
+    struct Refusal {
+        message: String,
+    }
+
+    match serde_json::from_str::<Refusal>(body) {
+        Ok(refusal) => Error::Refused(refusal.message),
+        Err(_) => Error::Server(status),
+    }
+}
+
+fn notes_response(status: StatusCode, body: &str) -> Result<Vec<NoteResult>, Error> {
+    #[derive(Deserialize)]
+    #[serde(tag = "status", rename_all = "lowercase")]
+    enum Item {
+        Created {
+            id: Option<i64>,
+            token: Option<String>,
+        },
+        Invalid {
+            errors: HashMap<String, Vec<ValidationError>>,
+        },
+    }
+
+    let items: Vec<Item> = json_response(status, body)?;
+    Ok(items
+        .into_iter()
+        .map(|item| match item {
+            Item::Created { id, token } => {
+                NoteResult::Created(id.zip(token).map(|(id, token)| NoteCreated { id, token }))
+            }
+            Item::Invalid { errors } => NoteResult::Invalid(
+                errors
+                    .into_iter()
+                    .map(|(field, errors)| (field, messages(&errors)))
+                    .collect(),
+            ),
+        })
+        .collect())
+}
+
+/// The batch size to retry with, if the server answered that the batch had
+/// more than its `MAX_BATCH_SIZE` messages.
+fn smaller_batch_size(status: StatusCode, body: &str, sent: usize) -> Option<usize> {
+    #[derive(Deserialize)]
+    struct TooLarge {
+        max: usize,
+    }
+
+    if status != StatusCode::PAYLOAD_TOO_LARGE {
+        return None;
+    }
+    serde_json::from_str::<TooLarge>(body)
+        .ok()
+        .map(|too_large| too_large.max)
+        .filter(|max| *max > 0 && *max < sent)
+}
+
+fn json_response<T: DeserializeOwned>(status: StatusCode, body: &str) -> Result<T, Error> {
+    if status != StatusCode::OK {
+        return Err(Error::Server(status));
+    }
+    serde_json::from_str(body).map_err(|err| Error::Decode(err.to_string()))
+}
+
+/// The answer to a signed request, the server tells why it refused the signature.
+#[cfg(feature = "signing")]
+fn signed_response<T: DeserializeOwned>(status: StatusCode, body: &str) -> Result<T, Error> {
+    match status {
+        StatusCode::UNAUTHORIZED => Err(refusal(status, body)),
+        _ => json_response(status, body),
+    }
+}
+
+fn count_response(status: StatusCode, body: &str) -> Result<i64, Error> {
+    if status != StatusCode::OK {
+        return Err(Error::Server(status));
+    }
+    body.trim()
+        .parse()
+        .map_err(|_| Error::Decode(format!("expected a count, got {:?}", body)))
+}
+
+#[test]
+fn endpoint_urls() {
+    let endpoint = Endpoint::new("https://ty.example/v0/");
+    assert_eq!(endpoint.note(), "https://ty.example/v0/note");
+    assert_eq!(endpoint.stats(), "https://ty.example/v0");
+    assert_eq!(endpoint.identity(), "https://ty.example/v0/identity");
+    assert_eq!(
+        endpoint.detail("git lfs"),
+        "https://ty.example/v0/tool/git%20lfs/detail"
+    );
+}
+
+#[test]
+fn note_response_validation_errors() {
+    let body = r#"{"program":[{"code":"length","message":"Tool name can't be longer than 50 characters, sorry!","params":{"max":50}}]}"#;
+    match note_response(StatusCode::BAD_REQUEST, body) {
+        Err(Error::Validation(errors)) => assert_eq!(
+            errors["program"],
+            vec!["Tool name can't be longer than 50 characters, sorry!".to_string()]
+        ),
+        other => panic!("expected validation errors, got {:?}", other),
+    }
+}
+
+#[test]
+fn length_errors_count_characters() {
+    let message = ThankYouMessage {
+        program: "ty".to_string(),
+        note: Some("🙏".repeat(2049)),
+        ..Default::default()
+    };
+    match validate(&message) {
+        Err(Error::Validation(errors)) => assert_eq!(
+            errors["note"],
+            vec!["Note too long! Please keep it under 2048 characters. (it has 2049 characters)"]
+        ),
+        other => panic!("expected validation errors, got {:?}", other),
+    }
+}
+
+#[test]
+fn notes_response_per_message() {
+    let body = r#"[{"status":"created","id":7,"token":"secret"},{"status":"invalid","errors":{"program":[{"code":"length","message":"Input needs to be at least one character long","params":{"min":1,"value":""}}]}}]"#;
+    let results = notes_response(StatusCode::OK, body).unwrap();
+    assert_eq!(
+        results[0],
+        NoteResult::Created(Some(NoteCreated {
+            id: 7,
+            token: "secret".to_string()
+        }))
+    );
+    match &results[1] {
+        NoteResult::Invalid(errors) => assert_eq!(
+            errors["program"],
+            vec!["Input needs to be at least one character long (it has 0 characters)".to_string()]
+        ),
+        other => panic!("expected validation errors, got {:?}", other),
+    }
+}
+
+#[test]
+fn batch_size_is_reduced() {
+    let too_large = StatusCode::PAYLOAD_TOO_LARGE;
+    assert_eq!(
+        smaller_batch_size(too_large, r#"{"max":20}"#, 100),
+        Some(20)
+    );
+    assert_eq!(smaller_batch_size(too_large, r#"{"max":20}"#, 10), None);
+    assert_eq!(smaller_batch_size(too_large, "", 100), None);
+}
+
+#[test]
+fn change_response_refused() {
+    assert!(change_response(StatusCode::NO_CONTENT, "").is_ok());
+    match change_response(StatusCode::FORBIDDEN, r#"{"message":"too old"}"#) {
+        Err(Error::Refused(reason)) => assert_eq!(reason, "too old"),
+        other => panic!("expected a refusal, got {:?}", other),
+    }
+}
+
+#[test]
+fn count_response_not_a_number() {
+    assert_eq!(count_response(StatusCode::OK, "42").unwrap(), 42);
+    assert!(matches!(
+        count_response(StatusCode::OK, "\"\""),
+        Err(Error::Decode(_))
+    ));
+}
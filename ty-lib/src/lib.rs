@@ -1,7 +1,14 @@
+#[cfg(feature = "schemars")]
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use validator::Validate;
+use validator::{Validate, ValidationError};
 
-#[derive(Validate, Serialize, Deserialize, Debug)]
+/// How many characters a note may have, in the validation of the messages and
+/// wherever a note is written.
+pub const MAX_NOTE_LENGTH: u64 = 2048;
+
+#[derive(Validate, Serialize, Deserialize, Debug, Default)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub struct ThankYouMessage {
     #[validate(
         length(min = 1, message = "Input needs to be at least one character long"),
@@ -13,31 +20,328 @@ pub struct ThankYouMessage {
     pub program: String,
 
     #[validate(length(
-        max = 2048,
+        max = "MAX_NOTE_LENGTH",
         message = "Note too long! Please keep it under 2048 characters."
     ))]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub note: Option<String>,
+
+    /// Only the verified maintainers of the program can read the note.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub private: bool,
+
+    /// Sponsor links found next to the program on the sender's machine, to
+    /// help with figuring out whom to deliver the thank-you to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[validate(custom = "validate_funding")]
+    pub funding: Vec<String>,
+
+    /// The program's repository, as found on the sender's machine.
+    #[validate(
+        custom = "validate_web_link",
+        length(max = 255, message = "Urls can't be longer than 255 characters.")
+    )]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+}
+
+/// Header with the key that makes sending a thank-you again safe, see
+/// `POST /v0/note`. A replay gets the edit token of the note, so the key has to
+/// be random and kept as secret as the token.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Header with the hex encoded ed25519 public key of a signed request.
+pub const PUBLIC_KEY_HEADER: &str = "ty-public-key";
+/// Header with the hex encoded signature over `signed_bytes` of the request.
+pub const SIGNATURE_HEADER: &str = "ty-signature";
+/// Header with the time a request was signed, in seconds since the unix epoch.
+/// Signatures older than a few minutes are turned down, so a captured request
+/// can't be replayed later.
+pub const TIMESTAMP_HEADER: &str = "ty-timestamp";
+
+/// What a signature covers: the time it was made, the `Idempotency-Key` if
+/// the request has one, and the message serialized as json, like it's sent,
+/// each on its own line. The server serializes what it received again to
+/// check the signature, so whitespace or field order in the request body
+/// don't matter.
+pub fn signed_bytes<T: Serialize>(
+    timestamp: i64,
+    idempotency_key: Option<&str>,
+    message: &T,
+) -> Vec<u8> {
+    let mut bytes =
+        format!("{}\n{}\n", timestamp, idempotency_key.unwrap_or_default()).into_bytes();
+    serde_json::to_writer(&mut bytes, message).expect("messages serialize to json");
+    bytes
+}
+
+/// Binds a public display name to a key, signed with that key. Without a
+/// display name, the key stays anonymous.
+#[derive(Validate, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct Identity {
+    /// Hex encoded ed25519 public key.
+    #[validate(length(equal = 64, message = "A public key has 64 hex digits."))]
+    pub public_key: String,
+
+    #[validate(length(
+        min = 1,
+        max = 50,
+        message = "Display names are 1 to 50 characters long."
+    ))]
+    pub display_name: Option<String>,
+}
+
+/// What the server answers when it collected a thank-you.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct NoteCreated {
+    pub id: i64,
+    /// Secret to edit or retract the note for a while, it's only handed out once.
+    pub token: String,
+}
+
+/// Replaces the note of a thank-you, `None` removes it.
+#[derive(Validate, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct NoteEdit {
+    #[validate(length(
+        max = "MAX_NOTE_LENGTH",
+        message = "Note too long! Please keep it under 2048 characters."
+    ))]
+    pub note: Option<String>,
+}
+
+/// A maintainer asks to be trusted with a program. The answer has a challenge
+/// to publish where the proof points to.
+#[derive(Validate, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct ClaimRequest {
+    #[validate(
+        length(min = 1, message = "Input needs to be at least one character long"),
+        length(
+            max = 50,
+            message = "Tool name can't be longer than 50 characters, sorry!"
+        )
+    )]
+    pub program: String,
+
+    #[validate(custom = "validate_proof")]
+    pub proof: Proof,
+}
+
+/// Where a maintainer publishes the challenge of a claim.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+#[serde(tag = "method", rename_all = "lowercase")]
+pub enum Proof {
+    /// A file with the challenge, served over https at
+    /// `/.well-known/ty-challenge` of the homepage's host, or as
+    /// `https://raw.githubusercontent.com/<owner>/<repo>/HEAD/.ty-challenge`.
+    File { url: String },
+    /// A TXT record with the challenge at `_ty-challenge.<domain>`.
+    Dns { domain: String },
+}
+
+fn validate_proof(proof: &Proof) -> Result<(), ValidationError> {
+    let valid = match proof {
+        Proof::File { url } => url.starts_with("https://") && url.len() <= 255,
+        Proof::Dns { domain } => {
+            !domain.is_empty()
+                && domain.len() <= 253
+                && domain
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+        }
+    };
+    if valid {
+        Ok(())
+    } else {
+        let mut error = ValidationError::new("proof");
+        error.message =
+            Some("Proofs need an https url or a domain name, of up to 255 characters.".into());
+        Err(error)
+    }
+}
+
+/// What the server answers to a `ClaimRequest`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct ClaimCreated {
+    pub id: i64,
+    /// To publish where the proof points to, before asking to verify the claim.
+    pub challenge: String,
+    /// Secret to act as the program's maintainer, once the claim is verified.
+    /// It's only handed out once.
+    pub token: String,
+}
+
+/// Where the thank-yous of a program are delivered to, set by its maintainers.
+#[derive(Validate, Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct Delivery {
+    #[validate(url(message = "The webhook needs to be a url."))]
+    pub webhook: Option<String>,
+
+    #[validate(email(message = "The email address isn't valid."))]
+    pub email: Option<String>,
+}
+
+/// What the maintainers tell about their program.
+#[derive(Validate, Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct Profile {
+    #[validate(length(
+        max = 500,
+        message = "The description can't be longer than 500 characters."
+    ))]
+    pub description: Option<String>,
+
+    #[validate(
+        custom = "validate_web_link",
+        length(max = 255, message = "Urls can't be longer than 255 characters.")
+    )]
+    pub homepage: Option<String>,
+
+    #[validate(
+        custom = "validate_web_link",
+        length(max = 255, message = "Urls can't be longer than 255 characters.")
+    )]
+    pub repository: Option<String>,
+
+    /// Where to sponsor or fund the program, most important first.
+    #[serde(default)]
+    #[validate(custom = "validate_funding")]
+    pub funding: Vec<String>,
+
+    #[validate(
+        custom = "validate_web_link",
+        length(max = 255, message = "Urls can't be longer than 255 characters.")
+    )]
+    pub logo: Option<String>,
+}
+
+fn validate_funding(funding: &[String]) -> Result<(), ValidationError> {
+    let valid = funding.len() <= 5
+        && funding
+            .iter()
+            .all(|link| link.len() <= 255 && is_web_link(link));
+    if valid {
+        Ok(())
+    } else {
+        let mut error = ValidationError::new("funding");
+        error.message = Some("Up to 5 funding links, each an http or https url.".into());
+        Err(error)
+    }
+}
+
+fn validate_web_link(link: &str) -> Result<(), ValidationError> {
+    if is_web_link(link) {
+        Ok(())
+    } else {
+        let mut error = ValidationError::new("url");
+        error.message = Some("Links need to be http or https urls.".into());
+        Err(error)
+    }
+}
+
+/// Whether the link is an http or https url. Links end up in the `href` of
+/// the tool's page, where `javascript:` and the like would run when clicked.
+pub fn is_web_link(link: &str) -> bool {
+    (link.starts_with("https://") || link.starts_with("http://")) && validator::validate_url(link)
+}
+
+/// `/v0/tool/{name}` when asked for json, the count with the profile.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct Tool {
+    pub program: String,
+    pub count: i64,
+    pub profile: Profile,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub struct ThankYouStats {
     pub program: String,
     pub count: i64,
     pub note_count: i64,
+    /// How many keys signed thank-yous, each counted once however often it
+    /// thanked.
+    #[serde(default)]
+    pub signer_count: i64,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
 pub struct ThankYouDetail {
     pub program: String,
+    /// Only the text of the notes, like servers before the replies answered.
     pub notes: Vec<String>,
+    /// The same notes, with the maintainers' replies to them.
+    #[serde(default)]
+    pub entries: Vec<NoteEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct NoteEntry {
+    pub id: i64,
+    pub note: String,
+    pub replies: Vec<Reply>,
+    /// The key that signed the note, with its display name if it has one.
+    /// Unsigned notes have none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<Identity>,
+}
+
+/// A maintainer's public reply to a note.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct Reply {
+    pub id: i64,
+    pub reply: String,
+}
+
+/// What a maintainer sends to reply to a note.
+#[derive(Validate, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct NoteReply {
+    #[validate(length(
+        min = 1,
+        max = 2048,
+        message = "Replies are 1 to 2048 characters long."
+    ))]
+    pub reply: String,
+}
+
+/// Asks for the replies to the thank-yous signed with a key, signed with that
+/// key. Requests older than a few minutes are turned down, so a signed request
+/// can't be replayed later.
+#[derive(Validate, Serialize, Deserialize, Debug)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct InboxRequest {
+    #[validate(length(equal = 64, message = "A public key has 64 hex digits."))]
+    pub public_key: String,
+    /// When the request was signed, in seconds since the unix epoch.
+    pub timestamp: i64,
+}
+
+/// A signed thank-you that got replies.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(JsonSchema))]
+pub struct InboxEntry {
+    pub id: i64,
+    pub program: String,
+    pub note: Option<String>,
+    pub replies: Vec<Reply>,
 }
 
 #[test]
 fn program_length_good() {
     let ty_message = ThankYouMessage {
         program: "good".to_string(),
-        note: None,
+        ..Default::default()
     };
 
     assert!(ty_message.validate().is_ok())
@@ -47,7 +351,7 @@ fn program_length_good() {
 fn program_length_too_long() {
     let ty_message = ThankYouMessage {
         program: "Is this way too long or maybe just one character to long?".to_string(),
-        note: None,
+        ..Default::default()
     };
     assert!(ty_message.validate().is_err())
 }
@@ -57,6 +361,7 @@ fn note_too_long() {
     let ty_message = ThankYouMessage {
         program: "good".to_string(),
         note: Some(include_str!("bad_note").to_string()),
+        ..Default::default()
     };
     assert!(ty_message.validate().is_err())
 }
@@ -66,6 +371,7 @@ fn note_unicode_good() {
     let ty_message = ThankYouMessage {
         program: "good".to_string(),
         note: Some(include_str!("good_emoji_note").to_string()),
+        ..Default::default()
     };
     assert!(ty_message.validate().is_ok())
 }
@@ -75,15 +381,117 @@ fn note_unicode_long() {
     let ty_message = ThankYouMessage {
         program: "good".to_string(),
         note: Some(include_str!("bad_emoji_note").to_string()),
+        ..Default::default()
     };
     assert!(ty_message.validate().is_err())
 }
 
+#[test]
+fn signed_bytes_are_the_time_key_and_json() {
+    let ty_message = ThankYouMessage {
+        program: "good".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(
+        signed_bytes(1_609_459_200, Some("key"), &ty_message),
+        b"1609459200\nkey\n{\"program\":\"good\"}".to_vec()
+    );
+    assert_eq!(
+        signed_bytes(1_609_459_200, None, &ty_message),
+        b"1609459200\n\n{\"program\":\"good\"}".to_vec()
+    );
+}
+
+#[test]
+fn private_is_left_out_unless_set() {
+    let mut ty_message = ThankYouMessage {
+        program: "good".to_string(),
+        ..Default::default()
+    };
+    assert!(!serde_json::to_string(&ty_message)
+        .unwrap()
+        .contains("private"));
+    ty_message.private = true;
+    assert!(serde_json::to_string(&ty_message)
+        .unwrap()
+        .contains(r#""private":true"#));
+}
+
+#[test]
+fn proofs_are_validated() {
+    let claim = |proof| ClaimRequest {
+        program: "good".to_string(),
+        proof,
+    };
+    assert!(claim(Proof::File {
+        url: "https://example.com/.ty-challenge".to_string()
+    })
+    .validate()
+    .is_ok());
+    assert!(claim(Proof::File {
+        url: "http://example.com/.ty-challenge".to_string()
+    })
+    .validate()
+    .is_err());
+    assert!(claim(Proof::Dns {
+        domain: "example.com".to_string()
+    })
+    .validate()
+    .is_ok());
+    assert!(claim(Proof::Dns {
+        domain: "example.com/x".to_string()
+    })
+    .validate()
+    .is_err());
+}
+
+#[test]
+fn funding_links_are_validated() {
+    let profile = |funding: &[&str]| Profile {
+        funding: funding.iter().map(|link| link.to_string()).collect(),
+        ..Profile::default()
+    };
+    assert!(profile(&["https://github.com/sponsors/ty"])
+        .validate()
+        .is_ok());
+    assert!(profile(&["not a link"]).validate().is_err());
+    assert!(profile(&["https://example.com"; 6]).validate().is_err());
+}
+
 #[test]
 fn note_length_good() {
     let ty_message = ThankYouMessage {
         program: "good".to_string(),
         note: Some(include_str!("good_note").to_string()),
+        ..Default::default()
     };
     assert!(ty_message.validate().is_ok())
 }
+
+#[test]
+fn links_are_http_or_https() {
+    let profile = |link: &str| Profile {
+        homepage: Some(link.to_string()),
+        funding: vec![link.to_string()],
+        ..Profile::default()
+    };
+    assert!(profile("https://example.com").validate().is_ok());
+    assert!(profile("http://example.com/sponsor").validate().is_ok());
+
+    let errors = profile("javascript:alert(document.cookie)")
+        .validate()
+        .unwrap_err();
+    let errors = errors.field_errors();
+    assert!(errors.contains_key("homepage"));
+    assert!(errors.contains_key("funding"));
+    assert!(profile("data:text/html,<script>alert(1)</script>")
+        .validate()
+        .is_err());
+
+    let ty_message = ThankYouMessage {
+        program: "good".to_string(),
+        repository: Some("javascript:alert(1)".to_string()),
+        ..Default::default()
+    };
+    assert!(ty_message.validate().is_err());
+}
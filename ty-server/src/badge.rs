@@ -0,0 +1,163 @@
+//! Shields-style svg badges with the thank-you count, for READMEs.
+//!
+//! There's no font to measure the text with, the widths of Verdana at 11px are
+//! estimated per character, which is close enough for labels and numbers.
+
+use serde::Deserialize;
+
+use crate::xml::escape;
+
+/// `?style=flat-square&color=orange&label=danke`, all optional.
+#[derive(Deserialize, Debug, Default)]
+pub struct Options {
+    #[serde(default)]
+    pub style: Style,
+    pub color: Option<String>,
+    pub label: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Style {
+    #[default]
+    Flat,
+    FlatSquare,
+}
+
+const DEFAULT_LABEL: &str = "thanks";
+const DEFAULT_COLOR: &str = "#4c1";
+const MAX_LABEL_LENGTH: usize = 50;
+
+pub fn render(count: i64, options: &Options) -> String {
+    let label: String = options
+        .label
+        .as_deref()
+        .unwrap_or(DEFAULT_LABEL)
+        .chars()
+        .take(MAX_LABEL_LENGTH)
+        .collect();
+    let message = compact(count);
+    let color = options
+        .color
+        .as_deref()
+        .and_then(color)
+        .unwrap_or_else(|| DEFAULT_COLOR.to_string());
+
+    let label_width = text_width(&label) + 10;
+    let message_width = text_width(&message) + 10;
+    let width = label_width + message_width;
+    let (radius, gradient) = match options.style {
+        Style::Flat => (
+            3,
+            r##"<linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>"##,
+        ),
+        Style::FlatSquare => (0, ""),
+    };
+    let shine = if gradient.is_empty() {
+        String::new()
+    } else {
+        format!(r#"<rect width="{}" height="20" fill="url(#s)"/>"#, width)
+    };
+
+    let label = escape(&label);
+    let message = escape(&message);
+    format!(
+        concat!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}">"#,
+            r#"<title>{label}: {message}</title>{gradient}"#,
+            r##"<clipPath id="r"><rect width="{width}" height="20" rx="{radius}" fill="#fff"/></clipPath>"##,
+            r##"<g clip-path="url(#r)"><rect width="{label_width}" height="20" fill="#555"/>"##,
+            r#"<rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/>{shine}</g>"#,
+            r##"<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11">"##,
+            r##"<text x="{label_x}" y="15" fill="#010101" fill-opacity=".3">{label}</text>"##,
+            r#"<text x="{label_x}" y="14">{label}</text>"#,
+            r##"<text x="{message_x}" y="15" fill="#010101" fill-opacity=".3">{message}</text>"##,
+            r#"<text x="{message_x}" y="14">{message}</text></g></svg>"#
+        ),
+        width = width,
+        label_width = label_width,
+        message_width = message_width,
+        label_x = label_width as f32 / 2.0,
+        message_x = label_width as f32 + message_width as f32 / 2.0,
+        radius = radius,
+        gradient = gradient,
+        shine = shine,
+        color = color,
+        label = label,
+        message = message,
+    )
+}
+
+/// `1234` becomes `1.2k`, rounded down so it never claims more than there is.
+pub fn compact(count: i64) -> String {
+    let (value, suffix) = match count {
+        count if count >= 1_000_000 => (count as f64 / 1_000_000.0, "M"),
+        count if count >= 1_000 => (count as f64 / 1_000.0, "k"),
+        count => return count.to_string(),
+    };
+    if value < 10.0 {
+        let value = format!("{:.1}", (value * 10.0).floor() / 10.0);
+        format!("{}{}", value.trim_end_matches(".0"), suffix)
+    } else {
+        format!("{}{}", value.floor(), suffix)
+    }
+}
+
+/// The named colors of shields, or a hex color without the `#`. Anything else
+/// falls back to the default, like shields does.
+fn color(color: &str) -> Option<String> {
+    let named = match color {
+        "brightgreen" | "success" => "#4c1",
+        "green" => "#97ca00",
+        "yellowgreen" => "#a4a61d",
+        "yellow" => "#dfb317",
+        "orange" | "important" => "#fe7d37",
+        "red" | "critical" => "#e05d44",
+        "blue" | "informational" => "#007ec6",
+        "lightgrey" | "lightgray" | "inactive" => "#9f9f9f",
+        "grey" | "gray" => "#555",
+        "blueviolet" => "#8a2be2",
+        _ => "",
+    };
+    if !named.is_empty() {
+        Some(named.to_string())
+    } else if [3, 6].contains(&color.len()) && color.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(format!("#{}", color))
+    } else {
+        None
+    }
+}
+
+fn text_width(text: &str) -> u32 {
+    let tenths: u32 = text
+        .chars()
+        .map(|c| match c {
+            'i' | 'j' | 'l' | '.' | ',' | ':' | ';' | '\'' | '|' | '!' | 'I' => 35,
+            'f' | 't' | 'r' | ' ' | '(' | ')' | '-' => 45,
+            'm' | 'w' | 'M' | 'W' => 100,
+            'A'..='Z' => 75,
+            '0'..='9' => 70,
+            _ => 65,
+        })
+        .sum();
+    tenths.div_ceil(10)
+}
+
+#[test]
+fn badges_are_compact_and_escaped() {
+    assert_eq!(compact(999), "999");
+    assert_eq!(compact(1000), "1k");
+    assert_eq!(compact(1234), "1.2k");
+    assert_eq!(compact(99_999), "99k");
+    assert_eq!(compact(1_250_000), "1.2M");
+
+    let options = Options {
+        style: Style::FlatSquare,
+        color: Some("orange".to_string()),
+        label: Some("<danke>".to_string()),
+    };
+    let svg = render(1234, &options);
+    assert!(svg.contains("&lt;danke&gt;: 1.2k"));
+    assert!(svg.contains(r##"fill="#fe7d37""##));
+    assert!(!svg.contains("linearGradient"));
+}
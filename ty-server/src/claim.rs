@@ -0,0 +1,428 @@
+//! Checks that a maintainer published the challenge of a claim where its
+//! proof points to. `CLAIM_CHECKER` picks how: `web` looks it up for real,
+//! `stub` accepts every claim, for running the server locally.
+//!
+//! A claim only counts if its proof is at the homepage or repository on record
+//! for the program, anyone could publish a challenge on a domain of their own.
+
+use std::future::Future;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::Url;
+use serde::Deserialize;
+use ty_lib::Proof;
+
+/// Files bigger than this aren't read any further.
+const MAX_FILE_SIZE: usize = 64 * 1024;
+
+/// Redirects followed, each checked like the url of the proof.
+const MAX_REDIRECTS: usize = 3;
+
+/// What a maintainer gets to see if the file couldn't be fetched. The reason
+/// is only logged, it would tell about the network the server is in.
+const FETCH_FAILED: &str = "Couldn't fetch the file of the proof.";
+
+/// Resolves to why the challenge wasn't found, if it wasn't.
+pub type Check<'a> = Pin<Box<dyn Future<Output = Result<(), String>> + Send + 'a>>;
+
+pub trait Checker: Send + Sync {
+    fn check<'a>(&'a self, proof: &'a Proof, challenge: &'a str) -> Check<'a>;
+}
+
+pub fn from_env() -> Arc<dyn Checker> {
+    match std::env::var("CLAIM_CHECKER").as_deref() {
+        Ok("stub") => Arc::new(Stub),
+        Ok("web") | Err(_) => Arc::new(Web::new(
+            std::env::var("CLAIM_DNS_RESOLVER")
+                .unwrap_or_else(|_| "https://dns.google/resolve".to_string()),
+        )),
+        Ok(other) => panic!("unknown CLAIM_CHECKER {}, use web or stub", other),
+    }
+}
+
+/// Accepts every claim, never use it in production.
+pub struct Stub;
+
+impl Checker for Stub {
+    fn check<'a>(&'a self, proof: &'a Proof, _challenge: &'a str) -> Check<'a> {
+        println!("CLAIM_CHECKER is stub, accepting {:?} unchecked", proof);
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Fetches files over https and looks up TXT records with a DNS over HTTPS
+/// resolver that answers in json, like `https://dns.google/resolve`.
+pub struct Web {
+    http: reqwest::Client,
+    resolver: String,
+}
+
+impl Web {
+    pub fn new(resolver: String) -> Self {
+        Web {
+            http: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .expect("Could not build http client."),
+            resolver,
+        }
+    }
+
+    async fn file(&self, url: &str, challenge: &str) -> Result<(), String> {
+        let mut url = Url::parse(url).map_err(|_| FETCH_FAILED.to_string())?;
+        let mut redirects = 0;
+        let mut response = loop {
+            if let Err(reason) = check_public(&url).await {
+                println!("Not fetching {}: {}", url, reason);
+                return Err(FETCH_FAILED.to_string());
+            }
+            let response = self.http.get(url.clone()).send().await.map_err(|err| {
+                println!("Couldn't fetch {}: {}", url, err);
+                FETCH_FAILED.to_string()
+            })?;
+            if !response.status().is_redirection() || redirects == MAX_REDIRECTS {
+                break response;
+            }
+
+            redirects += 1;
+            url = match response
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| url.join(location).ok())
+            {
+                Some(location) => location,
+                None => break response,
+            };
+        };
+        if !response.status().is_success() {
+            println!("Couldn't fetch {}: {}", url, response.status());
+            return Err(FETCH_FAILED.to_string());
+        }
+
+        let mut content = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|err| {
+            println!("Couldn't fetch {}: {}", url, err);
+            FETCH_FAILED.to_string()
+        })? {
+            content.extend_from_slice(&chunk);
+            if content.len() > MAX_FILE_SIZE {
+                break;
+            }
+        }
+
+        if String::from_utf8_lossy(&content).contains(challenge) {
+            Ok(())
+        } else {
+            Err("The challenge isn't in the file of the proof.".to_string())
+        }
+    }
+
+    async fn dns(&self, domain: &str, challenge: &str) -> Result<(), String> {
+        let name = format!("_ty-challenge.{}", domain);
+        let answer: DnsAnswer = self
+            .http
+            .get(&self.resolver)
+            .query(&[("name", name.as_str()), ("type", "TXT")])
+            .header("accept", "application/dns-json")
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| {
+                println!("Couldn't look up {}: {}", name, err);
+                format!("Couldn't look up {}.", name)
+            })?
+            .json()
+            .await
+            .map_err(|err| {
+                println!("Couldn't look up {}: {}", name, err);
+                format!("Couldn't look up {}.", name)
+            })?;
+
+        if answer
+            .txt_records()
+            .iter()
+            .any(|txt| txt.trim() == challenge)
+        {
+            Ok(())
+        } else {
+            Err(format!("No TXT record of {} has the challenge.", name))
+        }
+    }
+}
+
+impl Checker for Web {
+    fn check<'a>(&'a self, proof: &'a Proof, challenge: &'a str) -> Check<'a> {
+        Box::pin(async move {
+            match proof {
+                Proof::File { url } => self.file(url, challenge).await,
+                Proof::Dns { domain } => self.dns(domain, challenge).await,
+            }
+        })
+    }
+}
+
+/// Files are only fetched over https from the default port, of hosts that
+/// resolve to public addresses only, so a proof can't point into the network
+/// the server runs in. reqwest resolves the host once more when connecting,
+/// a dns server answering differently the second time isn't caught.
+async fn check_public(url: &Url) -> Result<(), String> {
+    if url.scheme() != "https" || url.port().is_some() {
+        return Err("not https on port 443".to_string());
+    }
+    // ipv6 addresses come in brackets
+    let host = url
+        .host_str()
+        .ok_or("no host")?
+        .trim_matches(|c| c == '[' || c == ']');
+    let addrs: Vec<_> = tokio::net::lookup_host((host, 443))
+        .await
+        .map_err(|err| err.to_string())?
+        .collect();
+
+    if addrs.is_empty() {
+        Err("no addresses".to_string())
+    } else if let Some(addr) = addrs.iter().find(|addr| !is_public(addr.ip())) {
+        Err(format!("{} isn't public", addr.ip()))
+    } else {
+        Ok(())
+    }
+}
+
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            let shared = a == 100 && (64..128).contains(&b);
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || shared
+                || a == 0)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                let unique_local = first & 0xfe00 == 0xfc00;
+                let link_local = first & 0xffc0 == 0xfe80;
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || unique_local
+                    || link_local)
+            }
+        },
+    }
+}
+
+/// Where the challenge file goes on the host of a homepage.
+const WELL_KNOWN_PATH: &str = "/.well-known/ty-challenge";
+/// Where the challenge file goes in a github repository, on its default branch.
+const GITHUB_FILE: &str = "HEAD/.ty-challenge";
+
+/// Whether the proof is at one of the program's links on record. Files have
+/// fixed places only the owners can write to: `/.well-known/ty-challenge` on
+/// the host of a homepage, or `.ty-challenge` on the default branch of a
+/// github repository, served raw. Anywhere below a link won't do, strangers
+/// can put text into the issues of a repository, or into the commits of a
+/// fork, which share the objects of the repository. TXT records have to be of
+/// the domain of one of the links.
+pub fn is_bound(proof: &Proof, records: &[&str]) -> bool {
+    let records: Vec<Url> = records
+        .iter()
+        .filter_map(|record| Url::parse(record).ok())
+        .filter(|record| record.host_str().is_some())
+        .collect();
+
+    match proof {
+        Proof::File { url } => {
+            let url = match Url::parse(url) {
+                Ok(url) => url,
+                Err(_) => return false,
+            };
+            records
+                .iter()
+                .filter_map(proof_file)
+                .any(|file| file.eq_ignore_ascii_case(url.as_str()))
+        }
+        Proof::Dns { domain } => {
+            let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+            records.iter().any(|record| {
+                let host = record.host_str().unwrap_or_default();
+                host == domain || host.ends_with(&format!(".{}", domain))
+            })
+        }
+    }
+}
+
+/// The one url a file proof for the link can be at.
+fn proof_file(record: &Url) -> Option<String> {
+    match record.host_str()? {
+        "github.com" => {
+            let mut segments = record.path_segments()?.filter(|s| !s.is_empty());
+            let owner = segments.next()?;
+            let repo = segments.next()?;
+            let repo = repo.strip_suffix(".git").unwrap_or(repo);
+            Some(format!(
+                "https://raw.githubusercontent.com/{}/{}/{}",
+                owner, repo, GITHUB_FILE
+            ))
+        }
+        host => Some(format!("https://{}{}", host, WELL_KNOWN_PATH)),
+    }
+}
+
+#[derive(Deserialize)]
+struct DnsAnswer {
+    #[serde(rename = "Answer", default)]
+    answer: Vec<DnsRecord>,
+}
+
+#[derive(Deserialize)]
+struct DnsRecord {
+    #[serde(rename = "type")]
+    kind: u16,
+    data: String,
+}
+
+impl DnsAnswer {
+    /// Some resolvers quote TXT records, long ones split into several strings,
+    /// others don't.
+    fn txt_records(&self) -> Vec<String> {
+        const TXT: u16 = 16;
+        self.answer
+            .iter()
+            .filter(|record| record.kind == TXT)
+            .map(|record| {
+                if record.data.contains('"') {
+                    record.data.split('"').skip(1).step_by(2).collect()
+                } else {
+                    record.data.clone()
+                }
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn txt_records_are_joined() {
+    let answer: DnsAnswer = serde_json::from_str(
+        r#"{"Status":0,"Answer":[
+            {"name":"_ty-challenge.example.com.","type":16,"TTL":300,"data":"\"abc\" \"def\""},
+            {"name":"_ty-challenge.example.com.","type":16,"TTL":300,"data":"ghi"},
+            {"name":"example.com.","type":5,"TTL":300,"data":"other.example.com."}
+        ]}"#,
+    )
+    .unwrap();
+    assert_eq!(answer.txt_records(), vec!["abcdef", "ghi"]);
+
+    let empty: DnsAnswer = serde_json::from_str(r#"{"Status":3}"#).unwrap();
+    assert!(empty.txt_records().is_empty());
+}
+
+#[test]
+fn proofs_are_bound_to_the_links_on_record() {
+    let records = [
+        "https://github.com/BurntSushi/ripgrep",
+        "https://docs.example.com/ripgrep/",
+    ];
+    let file = |url: &str| Proof::File {
+        url: url.to_string(),
+    };
+    let dns = |domain: &str| Proof::Dns {
+        domain: domain.to_string(),
+    };
+
+    assert!(is_bound(
+        &file("https://raw.githubusercontent.com/BurntSushi/ripgrep/HEAD/.ty-challenge"),
+        &records
+    ));
+    assert!(is_bound(
+        &file("https://docs.example.com/.well-known/ty-challenge"),
+        &records
+    ));
+    assert!(is_bound(&dns("example.com"), &records));
+
+    assert!(!is_bound(
+        &file("https://evil.example.net/.well-known/ty-challenge"),
+        &records
+    ));
+    // anyone can comment on an issue
+    assert!(!is_bound(
+        &file("https://github.com/BurntSushi/ripgrep/issues/123"),
+        &records
+    ));
+    // a commit in a fork, served through the repository
+    assert!(!is_bound(
+        &file("https://raw.githubusercontent.com/BurntSushi/ripgrep/8c1ab4a1b1e0e3bd7a0b5e4ab7bc0e3f5d0c9e2a/.ty-challenge"),
+        &records
+    ));
+    assert!(!is_bound(
+        &file("https://raw.githubusercontent.com/BurntSushi/ripgrep-fork/HEAD/.ty-challenge"),
+        &records
+    ));
+    assert!(!is_bound(
+        &file("https://docs.example.com/ripgrep/ty.txt"),
+        &records
+    ));
+    assert!(!is_bound(
+        &file("https://docs.example.com/.well-known/ty-challenge?x"),
+        &records
+    ));
+    assert!(!is_bound(&dns("evil.example.net"), &records));
+    assert!(!is_bound(&dns("example.com"), &[]));
+}
+
+#[tokio::test]
+async fn only_public_hosts_are_fetched() {
+    for ip in &[
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "0.0.0.0",
+        "::1",
+        "fe80::1",
+        "fd00::1",
+        "::ffff:127.0.0.1",
+    ] {
+        assert!(!is_public(ip.parse().unwrap()), "{}", ip);
+    }
+    assert!(is_public("93.184.216.34".parse().unwrap()));
+    assert!(is_public("2606:4700::1111".parse().unwrap()));
+    assert!(
+        check_public(&Url::parse("https://[2606:4700::1111]/ty.txt").unwrap())
+            .await
+            .is_ok()
+    );
+
+    for url in &[
+        "https://localhost/ty.txt",
+        "https://127.0.0.1/ty.txt",
+        "https://[::1]/ty.txt",
+        "https://93.184.216.34:8080/ty.txt",
+        "http://93.184.216.34/ty.txt",
+    ] {
+        assert!(
+            check_public(&Url::parse(url).unwrap()).await.is_err(),
+            "{}",
+            url
+        );
+    }
+    assert!(
+        check_public(&Url::parse("https://93.184.216.34/ty.txt").unwrap())
+            .await
+            .is_ok()
+    );
+}
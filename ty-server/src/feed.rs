@@ -0,0 +1,124 @@
+//! Atom feeds of the newest public notes, to follow a program, or all of them,
+//! in a feed reader without an account.
+
+use sqlx::types::chrono::NaiveDateTime;
+use urlencoding::encode;
+
+use crate::xml::escape;
+
+/// Ids must never change, so they name the project rather than wherever the
+/// server is reached at. The entries' are derived from the id of the note.
+const TAG: &str = "tag:ty.paulweissenbach.com,2021";
+
+pub struct Entry {
+    pub id: i64,
+    pub program: String,
+    pub note: String,
+    pub created: NaiveDateTime,
+    pub public_key: Option<String>,
+    pub display_name: Option<String>,
+}
+
+impl Entry {
+    /// Who signed the note, by display name or else by the start of the key.
+    fn author(&self) -> Option<String> {
+        let public_key = self.public_key.as_ref()?;
+        Some(match self.display_name {
+            Some(ref display_name) => display_name.clone(),
+            None => format!("{}…", &public_key[..public_key.len().min(8)]),
+        })
+    }
+}
+
+/// The feed of the program, or of all of them. Entries are expected newest
+/// first, links are relative to the server.
+pub fn render(program: Option<&str>, entries: &[Entry]) -> String {
+    let (title, id, link) = match program {
+        Some(program) => (
+            format!("Thank-yous for {}", program),
+            format!("{}:feed/{}", TAG, encode(program)),
+            format!("/tool/{}", encode(program)),
+        ),
+        None => (
+            "Thank-yous".to_string(),
+            format!("{}:feed", TAG),
+            "/".to_string(),
+        ),
+    };
+    // an empty feed still needs a date, the first one there is will do
+    let updated = entries.first().map_or_else(
+        || "1970-01-01T00:00:00Z".to_string(),
+        |entry| timestamp(&entry.created),
+    );
+
+    let mut feed = format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="utf-8"?>"#,
+            "\n",
+            r#"<feed xmlns="http://www.w3.org/2005/Atom">"#,
+            "<id>{}</id><title>{}</title><updated>{}</updated>",
+            r#"<link href="{}"/><author><name>ty</name></author>"#
+        ),
+        id,
+        escape(&title),
+        updated,
+        escape(&link)
+    );
+    for entry in entries {
+        let created = timestamp(&entry.created);
+        feed.push_str(&format!(
+            concat!(
+                "<entry><id>{}:note/{}</id><title>Thank you for {}</title>",
+                "<updated>{}</updated><published>{}</published>{}",
+                r#"<link href="/tool/{}"/><content type="text">{}</content></entry>"#
+            ),
+            TAG,
+            entry.id,
+            escape(&entry.program),
+            created,
+            created,
+            entry.author().map_or_else(String::new, |author| format!(
+                "<author><name>{}</name></author>",
+                escape(&author)
+            )),
+            escape(&encode(&entry.program)),
+            escape(&entry.note)
+        ));
+    }
+    feed.push_str("</feed>");
+    feed
+}
+
+/// `created` is stored without a time zone, by a database running in UTC.
+fn timestamp(created: &NaiveDateTime) -> String {
+    created.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+#[test]
+fn entries_have_stable_ids() {
+    let entries = [
+        Entry {
+            id: 42,
+            program: "rustc".to_string(),
+            note: "Thanks <3\u{8}".to_string(),
+            created: NaiveDateTime::from_timestamp(1_609_459_200, 0),
+            public_key: None,
+            display_name: None,
+        },
+        Entry {
+            id: 41,
+            program: "rustc".to_string(),
+            note: "Thanks".to_string(),
+            created: NaiveDateTime::from_timestamp(1_609_459_100, 0),
+            public_key: Some("ab".repeat(32)),
+            display_name: Some("Someone".to_string()),
+        },
+    ];
+    let feed = render(Some("rustc"), &entries);
+    assert!(feed.contains("<id>tag:ty.paulweissenbach.com,2021:note/42</id>"));
+    assert_eq!(feed.matches("<author>").count(), 2);
+    assert!(feed.contains("<author><name>Someone</name></author>"));
+    assert!(feed.contains("<updated>2021-01-01T00:00:00Z</updated>"));
+    assert!(feed.contains("Thanks &lt;3\u{fffd}</content>"));
+    assert!(render(None, &[]).contains("<id>tag:ty.paulweissenbach.com,2021:feed</id>"));
+}
@@ -5,9 +5,19 @@ use serde::de::DeserializeOwned;
 use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
 use std::convert::Infallible;
 use std::env;
+use std::sync::Arc;
+use std::time::Duration;
 use validator::Validate;
 use warp::{Filter, Rejection, Reply};
 
+mod badge;
+mod claim;
+mod feed;
+mod openapi;
+mod signature;
+mod token;
+mod xml;
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenv().ok();
@@ -26,18 +36,120 @@ async fn main() -> anyhow::Result<()> {
         );
     });
 
-    let db_pool = PgPoolOptions::new()
-        .max_connections(10)
-        .connect(&env::var("DATABASE_URL").expect("DATABASE_URL expected in environment"))
-        .await
-        .expect("Cannot connect to postgres");
+    let db_pool = connect_with_retry(
+        &env::var("DATABASE_URL").expect("DATABASE_URL expected in environment"),
+    )
+    .await?;
 
     setup_database(db_pool.clone())
         .await
         .expect("seting up database failed");
 
-    let index = warp::any().and(warp::fs::dir(
-        env::var("STATIC_DIR").expect("STATIC_DIR expected in environment"),
+    let static_dir = env::var("STATIC_DIR").expect("STATIC_DIR expected in environment");
+
+    let port: u16 = env::var("PORT")
+        .unwrap_or_else(|_| "8901".to_string())
+        .parse()
+        .expect("coudln't parse PORT into u16");
+
+    let shutdown_timeout = Duration::from_secs(
+        env::var("SHUTDOWN_TIMEOUT")
+            .unwrap_or_else(|_| "30".to_string())
+            .parse()
+            .expect("couldn't parse SHUTDOWN_TIMEOUT into u64"),
+    );
+
+    let max_batch_size: usize = env::var("MAX_BATCH_SIZE")
+        .unwrap_or_else(|_| "100".to_string())
+        .parse()
+        .expect("couldn't parse MAX_BATCH_SIZE into usize");
+
+    let edit_window = Duration::from_secs(
+        env::var("EDIT_WINDOW")
+            .unwrap_or_else(|_| "86400".to_string())
+            .parse()
+            .expect("couldn't parse EDIT_WINDOW into u64"),
+    );
+
+    let checker = claim::from_env();
+
+    // acts as the maintainer of every program
+    let admin_token_hash = env::var("ADMIN_TOKEN")
+        .ok()
+        .filter(|admin_token| !admin_token.is_empty())
+        .map(|admin_token| token::hash(&admin_token));
+
+    // keys the edit tokens derived from idempotency keys
+    let token_secret = env::var("TOKEN_SECRET")
+        .ok()
+        .filter(|token_secret| !token_secret.is_empty())
+        .unwrap_or_else(|| {
+            println!("TOKEN_SECRET isn't set, replays after a restart get tokens that don't work");
+            token::generate()
+        });
+
+    let (stop_accepting, stopped_accepting) = tokio::sync::oneshot::channel::<()>();
+
+    let (_, server) = warp::serve(
+        routes(
+            db_pool.clone(),
+            &static_dir,
+            max_batch_size,
+            edit_window,
+            checker,
+            admin_token_hash,
+            token_secret,
+        )
+        .with(log),
+    )
+    .bind_with_graceful_shutdown(([0, 0, 0, 0], port), async {
+        stopped_accepting.await.ok();
+    });
+    // not spawned, so giving up on it below drops the server with it
+    tokio::pin!(server);
+
+    tokio::select! {
+        _ = shutdown_signal() => {}
+        _ = &mut server => return Ok(()),
+    }
+    println!(
+        "Shutting down, waiting up to {}s for in-flight requests",
+        shutdown_timeout.as_secs()
+    );
+    stop_accepting.send(()).ok();
+
+    if tokio::time::timeout(shutdown_timeout, server)
+        .await
+        .is_err()
+    {
+        println!("In-flight requests did not finish in time, dropping them");
+    }
+
+    // connections dropped requests still hold would keep close() waiting
+    if tokio::time::timeout(shutdown_timeout, db_pool.close())
+        .await
+        .is_err()
+    {
+        println!("The database connections did not close in time");
+    }
+
+    Ok(())
+}
+
+fn routes(
+    db_pool: Pool<Postgres>,
+    static_dir: &str,
+    max_batch_size: usize,
+    edit_window: Duration,
+    checker: Arc<dyn claim::Checker>,
+    admin_token_hash: Option<String>,
+    token_secret: String,
+) -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let index = warp::any().and(warp::fs::dir(static_dir.to_string()));
+
+    // paths the spa routes on the client side, they all get the app itself
+    let spa_routes = warp::path("tool").and(warp::get()).and(warp::fs::file(
+        std::path::Path::new(static_dir).join("index.html"),
     ));
 
     let readme = warp::path("readme").map(|| {
@@ -47,36 +159,246 @@ async fn main() -> anyhow::Result<()> {
         ))
     });
 
-    let api = warp::path("note")
+    let openapi_spec = openapi::spec();
+
+    let api = warp::path!("note")
         .and(warp::post())
         .and(warp::body::content_length_limit(4096))
         .and(with_db(db_pool.clone()))
+        .and(idempotency_key())
+        .and(warp::any().map(move || token_secret.clone()))
+        .and(signature())
         .and(validated_from_json())
         .and_then(handlers::handle_post_ty_note)
         .recover(handle_rejection)
+        .or(warp::path!("notes")
+            .and(warp::post())
+            // as much per message as a single note may take
+            .and(warp::body::content_length_limit(
+                4096 * max_batch_size as u64,
+            ))
+            .and(with_db(db_pool.clone()))
+            .and(batch_from_json(max_batch_size))
+            .and_then(handlers::handle_post_ty_notes)
+            .recover(handle_rejection))
+        .or(warp::path!("note" / i64)
+            .and(warp::patch())
+            .and(warp::body::content_length_limit(4096))
+            .and(edit_token())
+            .and(with_db(db_pool.clone()))
+            .and(warp::any().map(move || edit_window))
+            .and(validated_from_json())
+            .and_then(handlers::handle_patch_note)
+            .recover(handle_rejection))
+        .or(warp::path!("note" / i64)
+            .and(warp::delete())
+            .and(edit_token())
+            .and(with_db(db_pool.clone()))
+            .and(warp::any().map(move || edit_window))
+            .and_then(handlers::handle_delete_note)
+            .recover(handle_rejection))
+        .or(warp::path!("identity")
+            .and(warp::put())
+            .and(warp::body::content_length_limit(4096))
+            .and(with_db(db_pool.clone()))
+            .and(signature())
+            .and(validated_from_json())
+            .and_then(handlers::handle_put_identity)
+            .recover(handle_rejection))
+        .or(warp::path!("identity" / String)
+            .and(warp::get())
+            .and(with_db(db_pool.clone()))
+            .and_then(handlers::handle_identity)
+            .recover(handle_rejection))
+        .or(warp::path!("claim")
+            .and(warp::post())
+            .and(warp::body::content_length_limit(4096))
+            .and(with_db(db_pool.clone()))
+            .and(validated_from_json())
+            .and_then(handlers::handle_post_claim)
+            .recover(handle_rejection))
+        .or(warp::path!("claim" / i64 / "verify")
+            .and(warp::post())
+            .and(with_db(db_pool.clone()))
+            .and(warp::any().map(move || checker.clone()))
+            .and_then(handlers::handle_verify_claim)
+            .recover(handle_rejection))
+        .or(warp::path!("claim" / i64 / "approve")
+            .and(warp::post())
+            .and(maintainer_token(admin_token_hash.clone()))
+            .and(with_db(db_pool.clone()))
+            .and_then(handlers::handle_approve_claim)
+            .recover(handle_rejection))
+        .or(warp::path!("note" / i64 / "reply")
+            .and(warp::post())
+            .and(warp::body::content_length_limit(4096))
+            .and(maintainer_token(admin_token_hash.clone()))
+            .and(with_db(db_pool.clone()))
+            .and(validated_from_json())
+            .and_then(handlers::handle_post_reply)
+            .recover(handle_rejection))
+        .or(warp::path!("inbox")
+            .and(warp::post())
+            .and(warp::body::content_length_limit(4096))
+            .and(with_db(db_pool.clone()))
+            .and(signature())
+            .and(validated_from_json())
+            .and_then(handlers::handle_inbox)
+            .recover(handle_rejection))
+        .or(warp::path!("tool" / String / "private")
+            .and(warp::get())
+            .and(maintainer_token(admin_token_hash.clone()))
+            .and(with_db(db_pool.clone()))
+            .and_then(handlers::handle_private_notes)
+            .recover(handle_rejection))
+        .or(warp::path!("tool" / String / "delivery")
+            .and(warp::get())
+            .and(maintainer_token(admin_token_hash.clone()))
+            .and(with_db(db_pool.clone()))
+            .and_then(handlers::handle_delivery)
+            .recover(handle_rejection))
+        .or(warp::path!("tool" / String / "delivery")
+            .and(warp::put())
+            .and(warp::body::content_length_limit(4096))
+            .and(maintainer_token(admin_token_hash.clone()))
+            .and(with_db(db_pool.clone()))
+            .and(validated_from_json())
+            .and_then(handlers::handle_put_delivery)
+            .recover(handle_rejection))
         .or(warp::path::end()
             .and(warp::get())
             .and(with_db(db_pool.clone()))
             .and_then(handlers::handle_info))
+        .or(warp::path!("tool" / String / "badge.svg")
+            .and(warp::get())
+            .and(warp::query::<badge::Options>())
+            .and(with_db(db_pool.clone()))
+            .and_then(handlers::handle_badge))
+        .or(warp::path!("tool" / String / "profile")
+            .and(warp::put())
+            .and(warp::body::content_length_limit(4096))
+            .and(maintainer_token(admin_token_hash))
+            .and(with_db(db_pool.clone()))
+            .and(validated_from_json())
+            .and_then(handlers::handle_put_profile)
+            .recover(handle_rejection))
         .or(warp::path!("tool" / String)
+            .and(warp::header::optional::<String>("accept"))
             .and(with_db(db_pool.clone()))
             .and_then(handlers::handle_count))
         .or(warp::path!("tool" / String / "detail")
             .and(with_db(db_pool.clone()))
-            .and_then(handlers::handle_detail));
+            .and_then(handlers::handle_detail))
+        .or(warp::path!("tool" / String / "feed.atom")
+            .and(warp::get())
+            .and(with_db(db_pool.clone()))
+            .and_then(handlers::handle_tool_feed))
+        .or(warp::path!("feed.atom")
+            .and(warp::get())
+            .and(with_db(db_pool.clone()))
+            .and_then(handlers::handle_feed))
+        .or(warp::path!("openapi.json")
+            .and(warp::get())
+            .map(move || warp::reply::json(&openapi_spec)));
 
-    let ty_api_v0 = warp::path("v0").and(api);
+    let ty_api_v0 = warp::path("v0").and(api).with(cors());
 
-    let port: u16 = env::var("PORT")
-        .unwrap_or_else(|_| "8901".to_string())
+    let health = warp::path!("healthz")
+        .and(warp::get())
+        .and_then(handlers::handle_liveness)
+        .or(warp::path!("readyz")
+            .and(warp::get())
+            .and(with_db(db_pool.clone()))
+            .and_then(handlers::handle_readiness));
+
+    health.or(index).or(ty_api_v0).or(readme).or(spa_routes)
+}
+
+/// Resolves on SIGTERM (what `docker stop` sends) or ctrl-c.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut sigterm = signal(SignalKind::terminate()).expect("couldn't listen for SIGTERM");
+        tokio::select! {
+            _ = sigterm.recv() => {},
+            _ = tokio::signal::ctrl_c() => {},
+        }
+    }
+
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c()
+        .await
+        .expect("couldn't listen for ctrl-c");
+}
+
+/// Connects to postgres, retrying with exponential backoff. Docker's `depends_on`
+/// only orders the startup, so the database might not accept connections yet.
+async fn connect_with_retry(database_url: &str) -> anyhow::Result<Pool<Postgres>> {
+    let max_attempts: u32 = env::var("DATABASE_CONNECT_ATTEMPTS")
+        .unwrap_or_else(|_| "10".to_string())
         .parse()
-        .expect("coudln't parse PORT into u16");
+        .expect("couldn't parse DATABASE_CONNECT_ATTEMPTS into u32");
 
-    warp::serve(warp::any().and(index.or(ty_api_v0).or(readme)).with(log))
-        .run(([0, 0, 0, 0], port))
-        .await;
+    let pool = PgPoolOptions::new()
+        .max_connections(10)
+        .connect_timeout(Duration::from_secs(5))
+        .connect_lazy(database_url)?;
 
-    Ok(())
+    let mut backoff = Duration::from_millis(500);
+    let mut attempt = 1;
+
+    loop {
+        match pool.acquire().await {
+            Ok(_) => return Ok(pool),
+            Err(err) if attempt < max_attempts => {
+                println!(
+                    "Cannot connect to postgres (attempt {}/{}): {}. Retrying in {}ms",
+                    attempt,
+                    max_attempts,
+                    err,
+                    backoff.as_millis()
+                );
+                tokio::time::delay_for(backoff).await;
+                backoff = (backoff * 2).min(Duration::from_secs(30));
+                attempt += 1;
+            }
+            Err(err) => {
+                return Err(anyhow::Error::new(err).context(format!(
+                    "Cannot connect to postgres after {} attempts",
+                    max_attempts
+                )))
+            }
+        }
+    }
+}
+
+/// CORS for the api, so the frontend can be hosted on another domain.
+/// `CORS_ALLOWED_ORIGINS` takes a comma separated list of origins, if it is not
+/// set, every origin is allowed.
+fn cors() -> warp::cors::Cors {
+    let cors = warp::cors()
+        .allow_methods(vec!["GET", "POST", "PUT", "PATCH", "DELETE"])
+        .allow_headers(vec![
+            "content-type",
+            "authorization",
+            ty_lib::IDEMPOTENCY_KEY_HEADER,
+            ty_lib::PUBLIC_KEY_HEADER,
+            ty_lib::SIGNATURE_HEADER,
+            ty_lib::TIMESTAMP_HEADER,
+        ])
+        .max_age(Duration::from_secs(3600));
+
+    match env::var("CORS_ALLOWED_ORIGINS") {
+        Ok(origins) => origins
+            .split(',')
+            .map(str::trim)
+            .filter(|origin| !origin.is_empty())
+            .fold(cors, |cors, origin| cors.allow_origin(origin)),
+        Err(_) => cors.allow_any_origin(),
+    }
+    .build()
 }
 
 fn with_db(
@@ -101,6 +423,112 @@ where
     })
 }
 
+/// A list of items, at most `max_batch_size` of them. The items are validated
+/// one by one in the handler, so one bad item doesn't fail the whole batch.
+fn batch_from_json<T>(
+    max_batch_size: usize,
+) -> impl Filter<Extract = (Vec<T>,), Error = Rejection> + Copy
+where
+    T: DeserializeOwned + Send,
+{
+    warp::body::json().and_then(move |json: Vec<T>| async move {
+        if json.len() > max_batch_size {
+            Err(warp::reject::custom(TYBatchTooLarge {
+                max: max_batch_size,
+            }))
+        } else {
+            Ok(json)
+        }
+    })
+}
+
+/// The optional `Idempotency-Key` header, clients pick a random one per
+/// thank-you, e.g. a uuid.
+fn idempotency_key() -> impl Filter<Extract = (Option<String>,), Error = Rejection> + Copy {
+    warp::header::optional::<String>(ty_lib::IDEMPOTENCY_KEY_HEADER).and_then(
+        |key: Option<String>| async move {
+            match key {
+                Some(key) if key.is_empty() || key.len() > 255 => {
+                    Err(warp::reject::custom(TYBadIdempotencyKey {}))
+                }
+                key => Ok(key),
+            }
+        },
+    )
+}
+
+/// The optional signature headers, all or none of them. Stale signatures are
+/// turned down here, the signature itself is checked by the handler, against
+/// the body it signs.
+fn signature() -> impl Filter<Extract = (Option<signature::Signed>,), Error = Rejection> + Copy {
+    warp::header::optional::<String>(ty_lib::PUBLIC_KEY_HEADER)
+        .and(warp::header::optional::<String>(ty_lib::SIGNATURE_HEADER))
+        .and(warp::header::optional::<String>(ty_lib::TIMESTAMP_HEADER))
+        .and_then(
+            |public_key: Option<String>, signature: Option<String>, timestamp: Option<String>| async move {
+                match (public_key, signature, timestamp) {
+                    (None, None, None) => Ok(None),
+                    (Some(public_key), Some(signature), Some(timestamp)) => {
+                        let timestamp: i64 = timestamp
+                            .parse()
+                            .map_err(|_| warp::reject::custom(TYBadSignature {}))?;
+                        if !signature::is_fresh(timestamp) {
+                            return Err(warp::reject::custom(TYExpiredSignature {}));
+                        }
+                        Ok(Some(signature::Signed {
+                            public_key,
+                            signature,
+                            timestamp,
+                        }))
+                    }
+                    _ => Err(warp::reject::custom(TYBadSignature {})),
+                }
+            },
+        )
+}
+
+/// The edit token from an `Authorization: Bearer <token>` header.
+fn edit_token() -> impl Filter<Extract = (String,), Error = Rejection> + Copy {
+    warp::header::optional::<String>("authorization").and_then(
+        |header: Option<String>| async move {
+            bearer(header).ok_or_else(|| warp::reject::custom(TYUnauthorized {}))
+        },
+    )
+}
+
+/// Who may act for a program: the holder of a verified claim's token, or the
+/// admin, for every program.
+#[derive(Debug, Clone)]
+enum Maintainer {
+    Admin,
+    Token(String),
+}
+
+/// The maintainer token from an `Authorization: Bearer <token>` header.
+/// Whether it's for the program is up to the handler.
+fn maintainer_token(
+    admin_token_hash: Option<String>,
+) -> impl Filter<Extract = (Maintainer,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("authorization").and_then(move |header: Option<String>| {
+        let admin_token_hash = admin_token_hash.clone();
+        async move {
+            let token = bearer(header).ok_or_else(|| warp::reject::custom(TYNotMaintainer {}))?;
+            if admin_token_hash == Some(token::hash(&token)) {
+                Ok::<_, Rejection>(Maintainer::Admin)
+            } else {
+                Ok(Maintainer::Token(token))
+            }
+        }
+    })
+}
+
+fn bearer(header: Option<String>) -> Option<String> {
+    header
+        .as_deref()
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string())
+}
+
 #[derive(Debug)]
 struct TYValidationError {
     errors: validator::ValidationErrors,
@@ -114,6 +542,83 @@ impl TYValidationError {
 
 impl warp::reject::Reject for TYValidationError {}
 
+#[derive(Debug)]
+struct TYBatchTooLarge {
+    max: usize,
+}
+
+impl warp::reject::Reject for TYBatchTooLarge {}
+
+#[derive(Debug)]
+struct TYBadIdempotencyKey {}
+
+impl warp::reject::Reject for TYBadIdempotencyKey {}
+
+/// A signed thank-you without an `Idempotency-Key`, a replay of it would be
+/// counted again.
+#[derive(Debug)]
+struct TYMissingIdempotencyKey {}
+
+impl warp::reject::Reject for TYMissingIdempotencyKey {}
+
+/// The edit token is missing or doesn't belong to the note.
+#[derive(Debug)]
+struct TYUnauthorized {}
+
+impl warp::reject::Reject for TYUnauthorized {}
+
+/// The signature is missing, incomplete or doesn't match what was sent.
+#[derive(Debug)]
+struct TYBadSignature {}
+
+impl warp::reject::Reject for TYBadSignature {}
+
+#[derive(Debug)]
+struct TYIdentityNotFound {}
+
+impl warp::reject::Reject for TYIdentityNotFound {}
+
+/// The identity was set with a newer signature already.
+#[derive(Debug)]
+struct TYStaleIdentity {}
+
+impl warp::reject::Reject for TYStaleIdentity {}
+
+/// A signed request that is too old, or from the future.
+#[derive(Debug)]
+struct TYExpiredSignature {}
+
+impl warp::reject::Reject for TYExpiredSignature {}
+
+/// The maintainer token is missing or not for the program.
+#[derive(Debug)]
+struct TYNotMaintainer {}
+
+impl warp::reject::Reject for TYNotMaintainer {}
+
+#[derive(Debug)]
+struct TYClaimNotFound {}
+
+impl warp::reject::Reject for TYClaimNotFound {}
+
+/// The proof isn't at a link on record for the program and no admin approved
+/// the claim.
+#[derive(Debug)]
+struct TYUnboundClaim {}
+
+impl warp::reject::Reject for TYUnboundClaim {}
+
+#[derive(Debug)]
+struct TYNoteNotFound {}
+
+impl warp::reject::Reject for TYNoteNotFound {}
+
+/// The note is older than the `EDIT_WINDOW`.
+#[derive(Debug)]
+struct TYEditWindowOver {}
+
+impl warp::reject::Reject for TYEditWindowOver {}
+
 #[derive(Debug)]
 struct TYDatabaseError {}
 
@@ -125,141 +630,1011 @@ async fn handle_rejection(err: Rejection) -> Result<impl Reply, Rejection> {
             warp::reply::json(&e.errors),
             StatusCode::BAD_REQUEST,
         ))
+    } else if let Some(e) = err.find::<TYBatchTooLarge>() {
+        Ok(warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({ "max": e.max })),
+            StatusCode::PAYLOAD_TOO_LARGE,
+        ))
+    } else if err.find::<TYBadIdempotencyKey>().is_some() {
+        Ok(message_reply(
+            "The Idempotency-Key needs to be 1 to 255 characters long.",
+            StatusCode::BAD_REQUEST,
+        ))
+    } else if err.find::<TYMissingIdempotencyKey>().is_some() {
+        Ok(message_reply(
+            "Signed thank-yous need an Idempotency-Key, so a replay isn't counted twice.",
+            StatusCode::BAD_REQUEST,
+        ))
+    } else if err.find::<TYUnauthorized>().is_some() {
+        Ok(message_reply(
+            "The edit token is missing or wrong.",
+            StatusCode::UNAUTHORIZED,
+        ))
+    } else if err.find::<TYBadSignature>().is_some() {
+        Ok(message_reply(
+            "The signature is missing or doesn't match.",
+            StatusCode::UNAUTHORIZED,
+        ))
+    } else if err.find::<TYIdentityNotFound>().is_some() {
+        Ok(message_reply(
+            "No one set a display name for this key.",
+            StatusCode::NOT_FOUND,
+        ))
+    } else if err.find::<TYStaleIdentity>().is_some() {
+        Ok(message_reply(
+            "The identity was set with a newer signature already.",
+            StatusCode::CONFLICT,
+        ))
+    } else if err.find::<TYExpiredSignature>().is_some() {
+        Ok(message_reply(
+            "The request was signed too long ago, is the clock right?",
+            StatusCode::UNAUTHORIZED,
+        ))
+    } else if err.find::<TYNotMaintainer>().is_some() {
+        Ok(message_reply(
+            "The maintainer token is missing or not for this program.",
+            StatusCode::UNAUTHORIZED,
+        ))
+    } else if err.find::<TYClaimNotFound>().is_some() {
+        Ok(message_reply(
+            "There is no claim with this id.",
+            StatusCode::NOT_FOUND,
+        ))
+    } else if err.find::<TYUnboundClaim>().is_some() {
+        Ok(message_reply(
+            "The proof has to be at the homepage or repository of the program, or an admin has to approve the claim.",
+            StatusCode::FORBIDDEN,
+        ))
+    } else if err.find::<TYNoteNotFound>().is_some() {
+        Ok(message_reply(
+            "There is no note with this id.",
+            StatusCode::NOT_FOUND,
+        ))
+    } else if err.find::<TYEditWindowOver>().is_some() {
+        Ok(message_reply(
+            "The note can't be changed anymore, it's too old.",
+            StatusCode::FORBIDDEN,
+        ))
     } else {
         Err(err)
     }
 }
 
+fn message_reply(message: &str, status: StatusCode) -> warp::reply::WithStatus<warp::reply::Json> {
+    warp::reply::with_status(
+        warp::reply::json(&serde_json::json!({ "message": message })),
+        status,
+    )
+}
+
 mod handlers {
     use http::StatusCode;
-    use sqlx::{Pool, Postgres};
-    use ty_lib::{ThankYouDetail, ThankYouMessage, ThankYouStats};
+    use serde_json::{json, Value};
+    use sqlx::{Done, Pool, Postgres};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use ty_lib::{
+        signed_bytes, ClaimCreated, ClaimRequest, Delivery, Identity, InboxEntry, InboxRequest,
+        NoteCreated, NoteEdit, NoteEntry, NoteReply, Profile, Proof, ThankYouDetail,
+        ThankYouMessage, ThankYouStats, Tool,
+    };
     use urlencoding::decode;
+    use validator::Validate;
     use warp::{Rejection, Reply};
 
-    use crate::TYDatabaseError;
+    use crate::badge;
+    use crate::claim::{self, Checker};
+    use crate::feed;
+    use crate::signature::Signed;
+    use crate::token;
+    use crate::Maintainer;
+    use crate::{
+        TYBadSignature, TYClaimNotFound, TYDatabaseError, TYEditWindowOver, TYIdentityNotFound,
+        TYMissingIdempotencyKey, TYNotMaintainer, TYNoteNotFound, TYStaleIdentity, TYUnauthorized,
+        TYUnboundClaim,
+    };
 
+    /// With an `Idempotency-Key`, a replayed request gets the answer of the
+    /// first one instead of counting the thank-you twice. Signed thank-yous
+    /// are stored with the key that signed them, they need an `Idempotency-Key`
+    /// and sign it too, so a captured one can't be counted again.
     pub async fn handle_post_ty_note(
         pool: Pool<Postgres>,
+        idempotency_key: Option<String>,
+        token_secret: String,
+        signed: Option<Signed>,
         ty_message: ThankYouMessage,
     ) -> Result<impl Reply, Rejection> {
-        match sqlx::query!(
+        let public_key = match signed {
+            Some(signed) => {
+                let idempotency_key = idempotency_key
+                    .as_deref()
+                    .ok_or_else(|| warp::reject::custom(TYMissingIdempotencyKey {}))?;
+                let bytes = signed_bytes(signed.timestamp, Some(idempotency_key), &ty_message);
+                Some(
+                    signed
+                        .verify(&bytes)
+                        .ok_or_else(|| warp::reject::custom(TYBadSignature {}))?,
+                )
+            }
+            None => None,
+        };
+        let token = match idempotency_key {
+            Some(ref key) => token::derive(&token_secret, key),
+            None => token::generate(),
+        };
+        let key_hash = idempotency_key.as_deref().map(token::hash);
+
+        let res = sqlx::query!(
             r#"
-                INSERT INTO ty (program, note)
-                VALUES ($1, $2)
+                INSERT INTO ty (program, note, private, edit_token_hash, idempotency_key_hash, public_key, funding, repository)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT (idempotency_key_hash) DO NOTHING
+                RETURNING id
             "#,
             ty_message.program,
-            ty_message.note
+            ty_message.note,
+            ty_message.private,
+            token::hash(&token),
+            key_hash,
+            public_key,
+            &ty_message.funding,
+            ty_message.repository
         )
-        .execute(&pool)
-        .await
-        {
-            Ok(_) => Ok(warp::reply::with_status(
-                warp::reply::json(&""),
+        .fetch_optional(&pool)
+        .await;
+
+        match (res, key_hash) {
+            (Ok(Some(rec)), _) => Ok(warp::reply::with_status(
+                warp::reply::json(&NoteCreated { id: rec.id, token }),
                 StatusCode::CREATED,
-            )),
-            Err(_) => Ok(warp::reply::with_status(
+            )
+            .into_response()),
+            // only the idempotency key can conflict
+            (Ok(None), Some(key_hash)) => replay(&pool, &key_hash, &ty_message, token).await,
+            _ => Ok(warp::reply::with_status(
                 warp::reply::json(&""),
                 StatusCode::INTERNAL_SERVER_ERROR,
-            )),
+            )
+            .into_response()),
         }
     }
 
-    pub async fn handle_count(
-        program: String,
+    /// Answers like the request that first used the idempotency key, if this
+    /// one is about the same thank-you.
+    async fn replay(
+        pool: &Pool<Postgres>,
+        key_hash: &str,
+        ty_message: &ThankYouMessage,
+        token: String,
+    ) -> Result<warp::reply::Response, Rejection> {
+        let rec = sqlx::query!(
+            "SELECT id, program, note FROM ty WHERE idempotency_key_hash = $1",
+            key_hash
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
+
+        if rec.program != ty_message.program || rec.note != ty_message.note {
+            return Ok(crate::message_reply(
+                "The Idempotency-Key was used for a different thank-you.",
+                StatusCode::UNPROCESSABLE_ENTITY,
+            )
+            .into_response());
+        }
+
+        Ok(warp::reply::with_header(
+            warp::reply::with_status(
+                warp::reply::json(&NoteCreated { id: rec.id, token }),
+                StatusCode::CREATED,
+            ),
+            "idempotent-replayed",
+            "true",
+        )
+        .into_response())
+    }
+
+    /// Stores the valid messages in one transaction, either all of them are
+    /// collected or none. Answers with a result per message, in order.
+    pub async fn handle_post_ty_notes(
         pool: Pool<Postgres>,
+        ty_messages: Vec<ThankYouMessage>,
     ) -> Result<impl Reply, Rejection> {
-        let program = match decode(&program) {
-            Ok(p) => p,
-            Err(err) => {
-                dbg!(err);
-                return Err(warp::reject::reject());
+        let validated: Vec<_> = ty_messages
+            .iter()
+            .map(|ty_message| (ty_message, ty_message.validate()))
+            .collect();
+
+        let stored = async {
+            let mut tx = pool.begin().await?;
+            let mut created = Vec::new();
+            for (ty_message, _) in validated.iter().filter(|(_, res)| res.is_ok()) {
+                let token = token::generate();
+                let rec = sqlx::query!(
+                    r#"
+                INSERT INTO ty (program, note, private, edit_token_hash, funding, repository)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                RETURNING id
+            "#,
+                    ty_message.program,
+                    ty_message.note,
+                    ty_message.private,
+                    token::hash(&token),
+                    &ty_message.funding,
+                    ty_message.repository
+                )
+                .fetch_one(&mut tx)
+                .await?;
+                created.push(NoteCreated { id: rec.id, token });
+            }
+            tx.commit().await?;
+            Ok::<_, sqlx::Error>(created)
+        };
+
+        let mut created = match stored.await {
+            Ok(created) => created.into_iter(),
+            Err(_) => {
+                return Ok(warp::reply::with_status(
+                    warp::reply::json(&""),
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                ))
             }
         };
 
+        let results: Vec<Value> = validated
+            .into_iter()
+            .map(|(_, res)| match res {
+                Ok(()) => {
+                    let created = created.next().expect("one per valid message");
+                    json!({ "status": "created", "id": created.id, "token": created.token })
+                }
+                Err(errors) => json!({ "status": "invalid", "errors": errors }),
+            })
+            .collect();
+
+        Ok(warp::reply::with_status(
+            warp::reply::json(&results),
+            StatusCode::OK,
+        ))
+    }
+
+    /// Sets the display name of a key, the identity has to be signed with it.
+    /// Only a newer signature replaces it, so a replayed one can't bring back
+    /// an old name.
+    pub async fn handle_put_identity(
+        pool: Pool<Postgres>,
+        signed: Option<Signed>,
+        identity: Identity,
+    ) -> Result<impl Reply, Rejection> {
+        let signed = signed.ok_or_else(|| warp::reject::custom(TYBadSignature {}))?;
+        let public_key = signed
+            .verify(&signed_bytes(signed.timestamp, None, &identity))
+            .filter(|public_key| public_key.eq_ignore_ascii_case(&identity.public_key))
+            .ok_or_else(|| warp::reject::custom(TYBadSignature {}))?;
+
         let res = sqlx::query!(
-            r#"SELECT COUNT(*) as "count!" FROM ty WHERE program = $1"#,
-            program
+            r#"
+                INSERT INTO identity (public_key, display_name, signed_at)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (public_key)
+                DO UPDATE SET
+                    display_name = EXCLUDED.display_name,
+                    signed_at = EXCLUDED.signed_at,
+                    updated = now()
+                WHERE identity.signed_at IS NULL OR identity.signed_at < EXCLUDED.signed_at
+            "#,
+            public_key,
+            identity.display_name,
+            signed.timestamp
         )
-        .fetch_one(&pool)
-        .await;
+        .execute(&pool)
+        .await
+        .map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
 
-        if let Ok(rec) = res {
-            Ok(rec.count.to_string())
-        } else {
-            // that is not a proper way to handle errors!
-            Ok(0.to_string())
+        if res.rows_affected() == 0 {
+            return Err(warp::reject::custom(TYStaleIdentity {}));
         }
+        Ok(StatusCode::NO_CONTENT)
     }
 
-    pub async fn handle_info(pool: Pool<Postgres>) -> Result<impl Reply, Rejection> {
-        let res = sqlx::query_as!(
-            ThankYouStats,
-            r#"
-                select 
-                    ty."program", 
-                    count(*) as "count!", 
-                    count(ty.note) as "note_count!"
-                from public.ty 
-                group by ty."program"
-                order by "count!" desc
-                limit 200;
-            "#
+    pub async fn handle_identity(
+        public_key: String,
+        pool: Pool<Postgres>,
+    ) -> Result<impl Reply, Rejection> {
+        let rec = sqlx::query!(
+            "SELECT public_key, display_name FROM identity WHERE public_key = $1",
+            public_key.to_lowercase()
         )
-        .fetch_all(&pool)
-        .await;
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| warp::reject::custom(TYDatabaseError {}))?
+        .ok_or_else(|| warp::reject::custom(TYIdentityNotFound {}))?;
 
-        if let Ok(stats) = res {
-            Ok(warp::reply::with_status(
-                warp::reply::json(&stats),
-                StatusCode::OK,
-            ))
-        } else {
-            Err(warp::reject::custom(TYDatabaseError {}))
-        }
+        Ok(warp::reply::json(&Identity {
+            public_key: rec.public_key,
+            display_name: rec.display_name,
+        }))
     }
 
-    pub async fn handle_detail(
-        program: String,
+    /// Starts a claim. Its token only works once the claim is verified.
+    pub async fn handle_post_claim(
         pool: Pool<Postgres>,
+        claim: ClaimRequest,
     ) -> Result<impl Reply, Rejection> {
-        let program = match decode(&program) {
-            Ok(p) => p,
+        let challenge = token::generate();
+        let token = token::generate();
+        let (method, location) = match claim.proof {
+            Proof::File { url } => ("file", url),
+            Proof::Dns { domain } => ("dns", domain),
+        };
+
+        let rec = sqlx::query!(
+            r#"
+                INSERT INTO claim (program, method, location, challenge, token_hash)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING id
+            "#,
+            claim.program,
+            method,
+            location,
+            challenge,
+            token::hash(&token)
+        )
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
+
+        Ok(warp::reply::with_status(
+            warp::reply::json(&ClaimCreated {
+                id: rec.id,
+                challenge,
+                token,
+            }),
+            StatusCode::CREATED,
+        ))
+    }
+
+    /// Looks for the published challenge, a verified claim makes its token a
+    /// maintainer token for the program. The proof has to be at the homepage
+    /// or repository of the program's profile, unless an admin approved it.
+    pub async fn handle_verify_claim(
+        id: i64,
+        pool: Pool<Postgres>,
+        checker: Arc<dyn Checker>,
+    ) -> Result<impl Reply, Rejection> {
+        let rec = sqlx::query!(
+            r#"
+                SELECT method, location, challenge, homepage, repository,
+                    verified IS NOT NULL as "verified!", approved IS NOT NULL as "approved!"
+                FROM claim
+                LEFT JOIN profile ON profile.program = claim.program
+                WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| warp::reject::custom(TYDatabaseError {}))?
+        .ok_or_else(|| warp::reject::custom(TYClaimNotFound {}))?;
+
+        if rec.verified {
+            return Ok(StatusCode::NO_CONTENT.into_response());
+        }
+
+        let proof = match rec.method.as_str() {
+            "file" => Proof::File { url: rec.location },
+            _ => Proof::Dns {
+                domain: rec.location,
+            },
+        };
+        let records: Vec<&str> = rec
+            .homepage
+            .iter()
+            .chain(rec.repository.iter())
+            .map(String::as_str)
+            .collect();
+        if !rec.approved && !claim::is_bound(&proof, &records) {
+            return Err(warp::reject::custom(TYUnboundClaim {}));
+        }
+        if let Err(reason) = checker.check(&proof, &rec.challenge).await {
+            return Ok(
+                crate::message_reply(&reason, StatusCode::UNPROCESSABLE_ENTITY).into_response(),
+            );
+        }
+
+        sqlx::query!("UPDATE claim SET verified = now() WHERE id = $1", id)
+            .execute(&pool)
+            .await
+            .map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
+
+        Ok(StatusCode::NO_CONTENT.into_response())
+    }
+
+    /// Lets a claim be verified though its proof isn't at a link on record for
+    /// the program. Only for the admin, who checked who's behind it.
+    pub async fn handle_approve_claim(
+        id: i64,
+        maintainer: Maintainer,
+        pool: Pool<Postgres>,
+    ) -> Result<impl Reply, Rejection> {
+        if !matches!(maintainer, Maintainer::Admin) {
+            return Err(warp::reject::custom(TYNotMaintainer {}));
+        }
+
+        let rec = sqlx::query!(
+            "UPDATE claim SET approved = now() WHERE id = $1 RETURNING id",
+            id
+        )
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
+
+        match rec {
+            Some(_) => Ok(StatusCode::NO_CONTENT),
+            None => Err(warp::reject::custom(TYClaimNotFound {})),
+        }
+    }
+
+    /// The private notes of a program, for its maintainers.
+    pub async fn handle_private_notes(
+        program: String,
+        maintainer: Maintainer,
+        pool: Pool<Postgres>,
+    ) -> Result<impl Reply, Rejection> {
+        let program = authorize_maintainer(&pool, &program, &maintainer).await?;
+
+        Ok(warp::reply::json(&notes(&pool, program, true).await?))
+    }
+
+    /// Replies to a note of a program the token is for.
+    pub async fn handle_post_reply(
+        id: i64,
+        maintainer: Maintainer,
+        pool: Pool<Postgres>,
+        reply: NoteReply,
+    ) -> Result<impl Reply, Rejection> {
+        let note = sqlx::query!("SELECT program FROM ty WHERE id = $1", id)
+            .fetch_optional(&pool)
+            .await
+            .map_err(|_| warp::reject::custom(TYDatabaseError {}))?
+            .ok_or_else(|| warp::reject::custom(TYNoteNotFound {}))?;
+        maintains(&pool, &note.program, &maintainer).await?;
+
+        let rec = sqlx::query!(
+            "INSERT INTO reply (note_id, reply) VALUES ($1, $2) RETURNING id",
+            id,
+            reply.reply
+        )
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
+
+        Ok(warp::reply::with_status(
+            warp::reply::json(&ty_lib::Reply {
+                id: rec.id,
+                reply: reply.reply,
+            }),
+            StatusCode::CREATED,
+        ))
+    }
+
+    /// The replies to the thank-yous signed with a key, for the one holding it.
+    pub async fn handle_inbox(
+        pool: Pool<Postgres>,
+        signed: Option<Signed>,
+        request: InboxRequest,
+    ) -> Result<impl Reply, Rejection> {
+        let signed = signed.ok_or_else(|| warp::reject::custom(TYBadSignature {}))?;
+        let public_key = signed
+            .verify(&signed_bytes(signed.timestamp, None, &request))
+            .filter(|public_key| public_key.eq_ignore_ascii_case(&request.public_key))
+            .ok_or_else(|| warp::reject::custom(TYBadSignature {}))?;
+
+        if request.timestamp != signed.timestamp {
+            return Err(warp::reject::custom(TYBadSignature {}));
+        }
+
+        let records = sqlx::query!(
+            r#"
+                select ty.id, ty.program, ty.note
+                from public.ty
+                where ty.public_key = $1
+                    and exists (select 1 from reply where reply.note_id = ty.id)
+                order by ty.id
+            "#,
+            public_key
+        )
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
+
+        let ids: Vec<i64> = records.iter().map(|row| row.id).collect();
+        let mut replies = replies(&pool, &ids).await?;
+        let entries: Vec<InboxEntry> = records
+            .into_iter()
+            .map(|row| InboxEntry {
+                replies: replies.remove(&row.id).unwrap_or_default(),
+                id: row.id,
+                program: row.program,
+                note: row.note,
+            })
+            .collect();
+
+        Ok(warp::reply::json(&entries))
+    }
+
+    pub async fn handle_delivery(
+        program: String,
+        maintainer: Maintainer,
+        pool: Pool<Postgres>,
+    ) -> Result<impl Reply, Rejection> {
+        let program = authorize_maintainer(&pool, &program, &maintainer).await?;
+
+        let delivery = sqlx::query_as!(
+            Delivery,
+            "SELECT webhook, email FROM delivery WHERE program = $1",
+            program
+        )
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
+
+        Ok(warp::reply::json(&delivery.unwrap_or_default()))
+    }
+
+    pub async fn handle_put_delivery(
+        program: String,
+        maintainer: Maintainer,
+        pool: Pool<Postgres>,
+        delivery: Delivery,
+    ) -> Result<impl Reply, Rejection> {
+        let program = authorize_maintainer(&pool, &program, &maintainer).await?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO delivery (program, webhook, email)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (program)
+                DO UPDATE SET webhook = EXCLUDED.webhook, email = EXCLUDED.email, updated = now()
+            "#,
+            program,
+            delivery.webhook,
+            delivery.email
+        )
+        .execute(&pool)
+        .await
+        .map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    /// For the program from the path, answers with its decoded name.
+    async fn authorize_maintainer(
+        pool: &Pool<Postgres>,
+        program: &str,
+        maintainer: &Maintainer,
+    ) -> Result<String, Rejection> {
+        let program = decode(program).map_err(|_| warp::reject::reject())?;
+        maintains(pool, &program, maintainer).await?;
+        Ok(program)
+    }
+
+    /// Maintainer tokens are scoped to the program of their claim.
+    async fn maintains(
+        pool: &Pool<Postgres>,
+        program: &str,
+        maintainer: &Maintainer,
+    ) -> Result<(), Rejection> {
+        let token = match maintainer {
+            Maintainer::Admin => return Ok(()),
+            Maintainer::Token(token) => token,
+        };
+
+        let rec = sqlx::query!(
+            r#"
+                SELECT EXISTS (
+                    SELECT 1 FROM claim
+                    WHERE program = $1 AND token_hash = $2 AND verified IS NOT NULL
+                ) as "maintainer!"
+            "#,
+            program,
+            token::hash(token)
+        )
+        .fetch_one(pool)
+        .await
+        .map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
+
+        if rec.maintainer {
+            Ok(())
+        } else {
+            Err(warp::reject::custom(TYNotMaintainer {}))
+        }
+    }
+
+    /// Replaces the note, with the token handed out when it was created.
+    pub async fn handle_patch_note(
+        id: i64,
+        token: String,
+        pool: Pool<Postgres>,
+        edit_window: Duration,
+        edit: NoteEdit,
+    ) -> Result<impl Reply, Rejection> {
+        authorize(&pool, id, &token, edit_window).await?;
+
+        sqlx::query!("UPDATE ty SET note = $2 WHERE id = $1", id, edit.note)
+            .execute(&pool)
+            .await
+            .map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    /// Retracts the note, with the token handed out when it was created.
+    pub async fn handle_delete_note(
+        id: i64,
+        token: String,
+        pool: Pool<Postgres>,
+        edit_window: Duration,
+    ) -> Result<impl Reply, Rejection> {
+        authorize(&pool, id, &token, edit_window).await?;
+
+        sqlx::query!("DELETE FROM ty WHERE id = $1", id)
+            .execute(&pool)
+            .await
+            .map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    /// Notes can be changed with their token, until they are older than the
+    /// edit window. Notes from before the tokens have none and stay as they are.
+    async fn authorize(
+        pool: &Pool<Postgres>,
+        id: i64,
+        token: &str,
+        edit_window: Duration,
+    ) -> Result<(), Rejection> {
+        let rec = sqlx::query!(
+            r#"
+                SELECT
+                    edit_token_hash,
+                    COALESCE(created > now() - make_interval(secs => $2), false) as "editable!"
+                FROM ty
+                WHERE id = $1
+            "#,
+            id,
+            edit_window.as_secs_f64()
+        )
+        .fetch_optional(pool)
+        .await
+        .map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
+
+        match rec {
+            None => Err(warp::reject::custom(TYNoteNotFound {})),
+            Some(rec) if rec.edit_token_hash != Some(token::hash(token)) => {
+                Err(warp::reject::custom(TYUnauthorized {}))
+            }
+            Some(rec) if !rec.editable => Err(warp::reject::custom(TYEditWindowOver {})),
+            Some(_) => Ok(()),
+        }
+    }
+
+    pub async fn handle_liveness() -> Result<impl Reply, Rejection> {
+        Ok(warp::reply::with_status("ok", StatusCode::OK))
+    }
+
+    /// Ready when the pool hands out a working connection and the schema set up
+    /// by `setup_database` is in place, down to its last step.
+    pub async fn handle_readiness(pool: Pool<Postgres>) -> Result<impl Reply, Rejection> {
+        let (table, column) = crate::LAST_MIGRATION;
+        let res = sqlx::query!(
+            r#"
+                SELECT EXISTS (
+                    SELECT 1 FROM information_schema.columns
+                    WHERE table_schema = 'public' AND table_name = $1 AND column_name = $2
+                ) as "ready!"
+            "#,
+            table,
+            column
+        )
+        .fetch_one(&pool)
+        .await;
+
+        match res {
+            Ok(rec) if rec.ready => Ok(warp::reply::with_status("ready", StatusCode::OK)),
+            _ => Ok(warp::reply::with_status(
+                "not ready",
+                StatusCode::SERVICE_UNAVAILABLE,
+            )),
+        }
+    }
+
+    /// Sets what the maintainers tell about the program.
+    pub async fn handle_put_profile(
+        program: String,
+        maintainer: Maintainer,
+        pool: Pool<Postgres>,
+        profile: Profile,
+    ) -> Result<impl Reply, Rejection> {
+        let program = authorize_maintainer(&pool, &program, &maintainer).await?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO profile (program, description, homepage, repository, funding, logo)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                ON CONFLICT (program)
+                DO UPDATE SET
+                    description = EXCLUDED.description,
+                    homepage = EXCLUDED.homepage,
+                    repository = EXCLUDED.repository,
+                    funding = EXCLUDED.funding,
+                    logo = EXCLUDED.logo,
+                    updated = now()
+            "#,
+            program,
+            profile.description,
+            profile.homepage,
+            profile.repository,
+            &profile.funding,
+            profile.logo
+        )
+        .execute(&pool)
+        .await
+        .map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    /// The count as text, like it always was, or with the profile as json if
+    /// that's what the client accepts.
+    pub async fn handle_count(
+        program: String,
+        accept: Option<String>,
+        pool: Pool<Postgres>,
+    ) -> Result<warp::reply::Response, Rejection> {
+        let program = match decode(&program) {
+            Ok(p) => p,
             Err(err) => {
                 dbg!(err);
                 return Err(warp::reject::reject());
             }
         };
 
-        let res = sqlx::query!(
+        let res = count(&pool, &program).await;
+
+        let wants_json = accept.is_some_and(|accept| accept.contains("application/json"));
+        if wants_json {
+            let count = res.map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
+            let profile = sqlx::query_as!(
+                Profile,
+                r#"
+                    SELECT description, homepage, repository, funding, logo
+                    FROM profile
+                    WHERE program = $1
+                "#,
+                program
+            )
+            .fetch_optional(&pool)
+            .await
+            .map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
+
+            return Ok(warp::reply::json(&Tool {
+                program,
+                count,
+                profile: profile.unwrap_or_default(),
+            })
+            .into_response());
+        }
+
+        if let Ok(count) = res {
+            Ok(count.to_string().into_response())
+        } else {
+            // that is not a proper way to handle errors!
+            Ok(0.to_string().into_response())
+        }
+    }
+
+    /// The count as an svg badge. Caches may keep it for a few minutes, like
+    /// the ones of shields.io.
+    pub async fn handle_badge(
+        program: String,
+        options: badge::Options,
+        pool: Pool<Postgres>,
+    ) -> Result<impl Reply, Rejection> {
+        let program = decode(&program).map_err(|_| warp::reject::reject())?;
+        let count = count(&pool, &program)
+            .await
+            .map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
+
+        Ok(warp::reply::with_header(
+            warp::reply::with_header(
+                badge::render(count, &options),
+                "content-type",
+                "image/svg+xml; charset=utf-8",
+            ),
+            "cache-control",
+            "public, max-age=300",
+        ))
+    }
+
+    pub async fn handle_tool_feed(
+        program: String,
+        pool: Pool<Postgres>,
+    ) -> Result<impl Reply, Rejection> {
+        let program = decode(&program).map_err(|_| warp::reject::reject())?;
+        feed(&pool, Some(program)).await
+    }
+
+    pub async fn handle_feed(pool: Pool<Postgres>) -> Result<impl Reply, Rejection> {
+        feed(&pool, None).await
+    }
+
+    /// The newest 50 public notes, of the program or of all of them.
+    async fn feed(pool: &Pool<Postgres>, program: Option<String>) -> Result<impl Reply, Rejection> {
+        let entries = sqlx::query_as!(
+            feed::Entry,
             r#"
-                select ty.note as "note!"
-                from public.ty 
+                select
+                    ty.id, ty.program, ty.note as "note!", ty.created as "created!",
+                    ty.public_key, identity.display_name
+                from public.ty
+                left join identity on identity.public_key = ty.public_key
                 where ty.note is not null
-                    and ty.program = $1
-                limit 200;
+                    and ty.private = false
+                    and ty.created is not null
+                    and ($1::VARCHAR is null or ty.program = $1)
+                order by ty.created desc
+                limit 50;
             "#,
             program
         )
-        .fetch_all(&pool)
-        .await;
+        .fetch_all(pool)
+        .await
+        .map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
+
+        Ok(warp::reply::with_header(
+            feed::render(program.as_deref(), &entries),
+            "content-type",
+            "application/atom+xml; charset=utf-8",
+        ))
+    }
 
-        if let Ok(records) = res {
-            let notes = records.iter().map(|row| row.note.to_string()).collect();
+    async fn count(pool: &Pool<Postgres>, program: &str) -> Result<i64, sqlx::Error> {
+        let rec = sqlx::query!(
+            r#"SELECT COUNT(*) as "count!" FROM ty WHERE program = $1"#,
+            program
+        )
+        .fetch_one(pool)
+        .await?;
+        Ok(rec.count)
+    }
 
-            let detail = ThankYouDetail { program, notes };
+    pub async fn handle_info(pool: Pool<Postgres>) -> Result<impl Reply, Rejection> {
+        let res = sqlx::query_as!(
+            ThankYouStats,
+            r#"
+                select 
+                    ty."program", 
+                    count(*) as "count!", 
+                    count(ty.note) as "note_count!",
+                    count(distinct ty.public_key) as "signer_count!"
+                from public.ty 
+                group by ty."program"
+                order by "count!" desc
+                limit 200;
+            "#
+        )
+        .fetch_all(&pool)
+        .await;
 
+        if let Ok(stats) = res {
             Ok(warp::reply::with_status(
-                warp::reply::json(&detail),
+                warp::reply::json(&stats),
                 StatusCode::OK,
             ))
         } else {
-            Err(warp::reject::reject())
-            // Ok(warp::reply::with_status(warp::reply::json(&""), StatusCode::INTERNAL_SERVER_ERROR))
+            Err(warp::reject::custom(TYDatabaseError {}))
         }
     }
+
+    pub async fn handle_detail(
+        program: String,
+        pool: Pool<Postgres>,
+    ) -> Result<impl Reply, Rejection> {
+        let program = match decode(&program) {
+            Ok(p) => p,
+            Err(err) => {
+                dbg!(err);
+                return Err(warp::reject::reject());
+            }
+        };
+
+        Ok(warp::reply::with_status(
+            warp::reply::json(&notes(&pool, program, false).await?),
+            StatusCode::OK,
+        ))
+    }
+
+    /// The public or the private notes of a program with their replies, at
+    /// most 200, newest first.
+    async fn notes(
+        pool: &Pool<Postgres>,
+        program: String,
+        private: bool,
+    ) -> Result<ThankYouDetail, Rejection> {
+        let records = sqlx::query!(
+            r#"
+                select ty.id, ty.note as "note!", ty.public_key, identity.display_name
+                from public.ty
+                left join identity on identity.public_key = ty.public_key
+                where ty.note is not null
+                    and ty.private = $2
+                    and ty.program = $1
+                order by ty.created desc
+                limit 200;
+            "#,
+            program,
+            private
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
+
+        let ids: Vec<i64> = records.iter().map(|row| row.id).collect();
+        let mut replies = replies(pool, &ids).await?;
+        let entries: Vec<NoteEntry> = records
+            .into_iter()
+            .map(|row| {
+                let display_name = row.display_name;
+                NoteEntry {
+                    replies: replies.remove(&row.id).unwrap_or_default(),
+                    id: row.id,
+                    note: row.note,
+                    signer: row.public_key.map(|public_key| Identity {
+                        public_key,
+                        display_name,
+                    }),
+                }
+            })
+            .collect();
+
+        Ok(ThankYouDetail {
+            program,
+            notes: entries.iter().map(|entry| entry.note.clone()).collect(),
+            entries,
+        })
+    }
+
+    /// The replies to the notes, oldest first, by note.
+    async fn replies(
+        pool: &Pool<Postgres>,
+        note_ids: &[i64],
+    ) -> Result<HashMap<i64, Vec<ty_lib::Reply>>, Rejection> {
+        let records = sqlx::query!(
+            "SELECT id, note_id, reply FROM reply WHERE note_id = ANY($1) ORDER BY id",
+            note_ids
+        )
+        .fetch_all(pool)
+        .await
+        .map_err(|_| warp::reject::custom(TYDatabaseError {}))?;
+
+        let mut replies: HashMap<i64, Vec<ty_lib::Reply>> = HashMap::new();
+        for row in records {
+            replies.entry(row.note_id).or_default().push(ty_lib::Reply {
+                id: row.id,
+                reply: row.reply,
+            });
+        }
+        Ok(replies)
+    }
 }
 
+/// The table and column the last step of `setup_database` adds, the server
+/// is only ready once they are there. Steps added later move it along.
+const LAST_MIGRATION: (&str, &str) = ("identity", "signed_at");
+
 async fn setup_database(pool: Pool<Postgres>) -> anyhow::Result<(), sqlx::Error> {
     sqlx::query!(
         r#"
@@ -274,5 +1649,233 @@ async fn setup_database(pool: Pool<Postgres>) -> anyhow::Result<(), sqlx::Error>
     .execute(&pool)
     .await?;
 
+    sqlx::query!("ALTER TABLE ty ADD COLUMN IF NOT EXISTS edit_token_hash VARCHAR(64);")
+        .execute(&pool)
+        .await?;
+
+    sqlx::query!(
+        "ALTER TABLE ty ADD COLUMN IF NOT EXISTS idempotency_key_hash VARCHAR(64) UNIQUE;"
+    )
+    .execute(&pool)
+    .await?;
+
+    // the key that signed the thank-you, if it was signed
+    sqlx::query!("ALTER TABLE ty ADD COLUMN IF NOT EXISTS public_key VARCHAR(64);")
+        .execute(&pool)
+        .await?;
+
+    sqlx::query!("ALTER TABLE ty ADD COLUMN IF NOT EXISTS private BOOLEAN NOT NULL DEFAULT false;")
+        .execute(&pool)
+        .await?;
+
+    // links the sender found locally, kept for finding the authors
+    sqlx::query!("ALTER TABLE ty ADD COLUMN IF NOT EXISTS funding TEXT[] NOT NULL DEFAULT '{}';")
+        .execute(&pool)
+        .await?;
+
+    sqlx::query!("ALTER TABLE ty ADD COLUMN IF NOT EXISTS repository VARCHAR(255);")
+        .execute(&pool)
+        .await?;
+
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS claim (
+            id BIGSERIAL PRIMARY KEY,
+            program VARCHAR(50) NOT NULL,
+            method VARCHAR(10) NOT NULL,
+            location VARCHAR(255) NOT NULL,
+            challenge VARCHAR(64) NOT NULL,
+            token_hash VARCHAR(64) NOT NULL UNIQUE,
+            created TIMESTAMP DEFAULT now(),
+            verified TIMESTAMP
+        );
+    "#
+    )
+    .execute(&pool)
+    .await?;
+
+    // set by the admin, for proofs that aren't at a link of the program's profile
+    sqlx::query!("ALTER TABLE claim ADD COLUMN IF NOT EXISTS approved TIMESTAMP;")
+        .execute(&pool)
+        .await?;
+
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS reply (
+            id BIGSERIAL PRIMARY KEY,
+            note_id BIGINT NOT NULL REFERENCES ty (id) ON DELETE CASCADE,
+            reply VARCHAR(2048) NOT NULL,
+            created TIMESTAMP DEFAULT now()
+        );
+    "#
+    )
+    .execute(&pool)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS profile (
+            program VARCHAR(50) PRIMARY KEY,
+            description VARCHAR(500),
+            homepage VARCHAR(255),
+            repository VARCHAR(255),
+            funding TEXT[] NOT NULL DEFAULT '{}',
+            logo VARCHAR(255),
+            updated TIMESTAMP DEFAULT now()
+        );
+    "#
+    )
+    .execute(&pool)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS delivery (
+            program VARCHAR(50) PRIMARY KEY,
+            webhook VARCHAR(2048),
+            email VARCHAR(255),
+            updated TIMESTAMP DEFAULT now()
+        );
+    "#
+    )
+    .execute(&pool)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        CREATE TABLE IF NOT EXISTS identity (
+            public_key VARCHAR(64) PRIMARY KEY,
+            display_name VARCHAR(50),
+            updated TIMESTAMP DEFAULT now()
+        );
+    "#
+    )
+    .execute(&pool)
+    .await?;
+
+    // when the identity was signed, only newer signatures replace it
+    sqlx::query!("ALTER TABLE identity ADD COLUMN IF NOT EXISTS signed_at BIGINT;")
+        .execute(&pool)
+        .await?;
+
     Ok(())
 }
+
+/// The routes with a database that never answers, queries fail and show up as
+/// 500s.
+#[cfg(test)]
+fn test_routes() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    let pool = PgPoolOptions::new()
+        .connect_timeout(Duration::from_millis(100))
+        .connect_lazy("postgres://localhost:1/ty")
+        .unwrap();
+    routes(
+        pool,
+        "/nonexistent",
+        100,
+        Duration::from_secs(60),
+        Arc::new(claim::Stub),
+        None,
+        "secret".to_string(),
+    )
+}
+
+#[tokio::test]
+async fn cors_allows_the_request_headers() {
+    let routes = test_routes();
+
+    let response = warp::test::request()
+        .method("OPTIONS")
+        .path("/v0/note")
+        .header("origin", "https://example.com")
+        .header("access-control-request-method", "POST")
+        .header(
+            "access-control-request-headers",
+            "content-type,idempotency-key,ty-public-key,ty-signature",
+        )
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn batches_are_only_posted_to_notes() {
+    let routes = test_routes();
+
+    let response = warp::test::request()
+        .method("POST")
+        .path("/v0/notes/anything")
+        .json(&serde_json::json!([{ "program": "" }]))
+        .reply(&routes)
+        .await;
+    // not routed, the static files only answer to GET
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn signed_requests_cannot_be_replayed() {
+    use ed25519_dalek::{Keypair, Signer};
+    use ty_lib::{signed_bytes, Identity, ThankYouMessage};
+
+    let routes = test_routes();
+    let keypair = Keypair::generate(&mut rand::rngs::OsRng);
+    let public_key = hex::encode(keypair.public.as_bytes());
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+
+    // captured an hour ago, replayed now
+    let identity = Identity {
+        public_key: public_key.clone(),
+        display_name: Some("Someone".to_string()),
+    };
+    let old = now - 3600;
+    let response = warp::test::request()
+        .method("PUT")
+        .path("/v0/identity")
+        .header(ty_lib::PUBLIC_KEY_HEADER, &public_key)
+        .header(
+            ty_lib::SIGNATURE_HEADER,
+            hex::encode(keypair.sign(&signed_bytes(old, None, &identity)).to_bytes()),
+        )
+        .header(ty_lib::TIMESTAMP_HEADER, old.to_string())
+        .json(&identity)
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // the old signature with a fresh timestamp doesn't match
+    let response = warp::test::request()
+        .method("PUT")
+        .path("/v0/identity")
+        .header(ty_lib::PUBLIC_KEY_HEADER, &public_key)
+        .header(
+            ty_lib::SIGNATURE_HEADER,
+            hex::encode(keypair.sign(&signed_bytes(old, None, &identity)).to_bytes()),
+        )
+        .header(ty_lib::TIMESTAMP_HEADER, now.to_string())
+        .json(&identity)
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // a fresh thank-you without a key could be counted again
+    let message = ThankYouMessage {
+        program: "rustc".to_string(),
+        ..Default::default()
+    };
+    let response = warp::test::request()
+        .method("POST")
+        .path("/v0/note")
+        .header(ty_lib::PUBLIC_KEY_HEADER, &public_key)
+        .header(
+            ty_lib::SIGNATURE_HEADER,
+            hex::encode(keypair.sign(&signed_bytes(now, None, &message)).to_bytes()),
+        )
+        .header(ty_lib::TIMESTAMP_HEADER, now.to_string())
+        .json(&message)
+        .reply(&routes)
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,732 @@
+//! OpenAPI 3 description of the v0 api, served at `/v0/openapi.json`.
+//!
+//! The schemas come from the ty-lib types (including their validation rules),
+//! the paths have to be kept in sync with `routes` by hand, which the tests
+//! below check, in both directions.
+
+use schemars::gen::SchemaSettings;
+use serde_json::{json, Value};
+use ty_lib::{
+    ClaimCreated, ClaimRequest, Delivery, Identity, InboxEntry, InboxRequest, NoteCreated,
+    NoteEdit, NoteReply, Profile, Reply, ThankYouDetail, ThankYouMessage, ThankYouStats, Tool,
+};
+
+pub fn spec() -> Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+    let message = gen.subschema_for::<ThankYouMessage>();
+    let messages = gen.subschema_for::<Vec<ThankYouMessage>>();
+    let created = gen.subschema_for::<NoteCreated>();
+    let edit = gen.subschema_for::<NoteEdit>();
+    let stats = gen.subschema_for::<Vec<ThankYouStats>>();
+    let detail = gen.subschema_for::<ThankYouDetail>();
+    let identity = gen.subschema_for::<Identity>();
+    let claim = gen.subschema_for::<ClaimRequest>();
+    let claim_created = gen.subschema_for::<ClaimCreated>();
+    let delivery = gen.subschema_for::<Delivery>();
+    let note_reply = gen.subschema_for::<NoteReply>();
+    let reply = gen.subschema_for::<Reply>();
+    let inbox = gen.subschema_for::<InboxRequest>();
+    let inbox_entries = gen.subschema_for::<Vec<InboxEntry>>();
+    let profile = gen.subschema_for::<Profile>();
+    let tool = gen.subschema_for::<Tool>();
+
+    let mut schemas = serde_json::to_value(gen.definitions()).expect("schemas are valid json");
+    schemas["ValidationErrors"] = json!({
+        "description": "Validation errors per field of the submitted message.",
+        "type": "object",
+        "additionalProperties": {
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {
+                    "code": { "type": "string" },
+                    "message": { "type": "string", "nullable": true },
+                    "params": { "type": "object" }
+                }
+            }
+        }
+    });
+
+    schemas["NoteResult"] = json!({
+        "description": "What became of one message of a batch.",
+        "type": "object",
+        "required": ["status"],
+        "properties": {
+            "status": { "type": "string", "enum": ["created", "invalid"] },
+            "id": { "type": "integer", "description": "The id of the created note." },
+            "token": { "type": "string", "description": "The edit token of the created note." },
+            "errors": { "$ref": "#/components/schemas/ValidationErrors" }
+        }
+    });
+
+    let program_name = json!({
+        "name": "name",
+        "in": "path",
+        "required": true,
+        "description": "Url encoded name of the program.",
+        "schema": { "type": "string", "minLength": 1, "maxLength": 50 }
+    });
+
+    let note_id = json!({
+        "name": "id",
+        "in": "path",
+        "required": true,
+        "description": "The id the note was created with.",
+        "schema": { "type": "integer" }
+    });
+
+    let claim_id = json!({
+        "name": "id",
+        "in": "path",
+        "required": true,
+        "description": "The id the claim was created with.",
+        "schema": { "type": "integer" }
+    });
+
+    let public_key = json!({
+        "name": "public_key",
+        "in": "path",
+        "required": true,
+        "description": "Hex encoded ed25519 public key.",
+        "schema": { "type": "string" }
+    });
+
+    let public_key_header = json!({
+        "name": "Ty-Public-Key",
+        "in": "header",
+        "required": false,
+        "description": "Hex encoded ed25519 public key of the signer.",
+        "schema": { "type": "string" }
+    });
+
+    let signature_header = json!({
+        "name": "Ty-Signature",
+        "in": "header",
+        "required": false,
+        "description": "Hex encoded ed25519 signature over the Ty-Timestamp, the Idempotency-Key (empty without one) and the request body serialized as compact json in the field order of the schema, each followed by a newline except the body.",
+        "schema": { "type": "string" }
+    });
+
+    let timestamp_header = json!({
+        "name": "Ty-Timestamp",
+        "in": "header",
+        "required": false,
+        "description": "When the request was signed, in seconds since the unix epoch. Signatures older than five minutes are turned down.",
+        "schema": { "type": "integer" }
+    });
+
+    let message_response = |description: &str| {
+        json!({
+            "description": description,
+            "content": {
+                "application/json": {
+                    "schema": {
+                        "type": "object",
+                        "properties": { "message": { "type": "string" } }
+                    }
+                }
+            }
+        })
+    };
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "ty - thank you",
+            "description": "Collects thank-you notes for the tools people use.",
+            "version": env!("CARGO_PKG_VERSION")
+        },
+        "servers": [{ "url": "/v0" }],
+        "paths": {
+            "/": {
+                "get": {
+                    "summary": "The most thanked programs, at most 200.",
+                    "responses": {
+                        "200": {
+                            "description": "Thank-you counts per program.",
+                            "content": { "application/json": { "schema": stats } }
+                        },
+                        "500": { "description": "The database couldn't be queried." }
+                    }
+                }
+            },
+            "/note": {
+                "post": {
+                    "summary": "Say thank you to a program, with an optional note.",
+                    "description": "Thank-yous can be signed with an ed25519 key, by sending the Ty-Public-Key, Ty-Signature and Ty-Timestamp headers. Signed thank-yous need an Idempotency-Key.",
+                    "parameters": [{
+                        "name": "Idempotency-Key",
+                        "in": "header",
+                        "required": false,
+                        "description": "A random key per thank-you, e.g. a random uuid. Sending the request again with the same key answers like the first time instead of counting the thank-you twice. The answer includes the edit token, so the key is a credential: keep it secret, never use counters or timestamps.",
+                        "schema": { "type": "string", "minLength": 1, "maxLength": 255 }
+                    }, public_key_header, signature_header, timestamp_header],
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": message } }
+                    },
+                    "responses": {
+                        "201": {
+                            "description": "The thank-you was collected. Replays of a request with an Idempotency-Key get the same answer, with an `Idempotent-Replayed: true` header.",
+                            "content": { "application/json": { "schema": created } }
+                        },
+                        "401": message_response("The signature is incomplete, too old or doesn't match the message."),
+                        "422": message_response("The Idempotency-Key was used for a different thank-you."),
+                        "400": {
+                            "description": "The message didn't pass validation, the Idempotency-Key is too long, or missing on a signed thank-you.",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/ValidationErrors" }
+                                }
+                            }
+                        },
+                        "500": { "description": "The thank-you couldn't be stored." }
+                    }
+                }
+            },
+            "/note/{id}": {
+                "patch": {
+                    "summary": "Replace the note of a thank-you, within the server's EDIT_WINDOW.",
+                    "parameters": [note_id],
+                    "security": [{ "editToken": [] }],
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": edit } }
+                    },
+                    "responses": {
+                        "204": { "description": "The note was replaced." },
+                        "400": {
+                            "description": "The note didn't pass validation.",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/ValidationErrors" }
+                                }
+                            }
+                        },
+                        "401": message_response("The edit token is missing or wrong."),
+                        "403": message_response("The note is older than the edit window."),
+                        "404": message_response("There is no note with this id."),
+                        "500": { "description": "The note couldn't be changed." }
+                    }
+                },
+                "delete": {
+                    "summary": "Retract a thank-you, within the server's EDIT_WINDOW.",
+                    "parameters": [note_id],
+                    "security": [{ "editToken": [] }],
+                    "responses": {
+                        "204": { "description": "The thank-you was retracted." },
+                        "401": message_response("The edit token is missing or wrong."),
+                        "403": message_response("The note is older than the edit window."),
+                        "404": message_response("There is no note with this id."),
+                        "500": { "description": "The thank-you couldn't be retracted." }
+                    }
+                }
+            },
+            "/note/{id}/reply": {
+                "post": {
+                    "summary": "Reply to a note, as a maintainer of its program. Replies are public.",
+                    "parameters": [note_id],
+                    "security": [{ "maintainerToken": [] }],
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": note_reply } }
+                    },
+                    "responses": {
+                        "201": {
+                            "description": "The reply was stored.",
+                            "content": { "application/json": { "schema": reply } }
+                        },
+                        "400": {
+                            "description": "The reply didn't pass validation.",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/ValidationErrors" }
+                                }
+                            }
+                        },
+                        "401": message_response("The maintainer token is missing or not for the note's program."),
+                        "404": message_response("There is no note with this id."),
+                        "500": { "description": "The reply couldn't be stored." }
+                    }
+                }
+            },
+            "/inbox": {
+                "post": {
+                    "summary": "The replies to the thank-yous signed with a key.",
+                    "description": "The request has to be signed with the key, and not be older than five minutes. The timestamp in the body has to match the Ty-Timestamp header.",
+                    "parameters": [public_key_header, signature_header, timestamp_header],
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": inbox } }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "The thank-yous with replies, oldest first.",
+                            "content": { "application/json": { "schema": inbox_entries } }
+                        },
+                        "400": {
+                            "description": "The request didn't pass validation.",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/ValidationErrors" }
+                                }
+                            }
+                        },
+                        "401": message_response("The signature is missing, doesn't match or is too old."),
+                        "500": { "description": "The database couldn't be queried." }
+                    }
+                }
+            },
+            "/notes": {
+                "post": {
+                    "summary": "Say thank you to several programs at once.",
+                    "description": "Each message is validated on its own, the valid ones are stored in one transaction. The server's MAX_BATCH_SIZE limits the number of messages, 100 by default.",
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": messages } }
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "A result per message, in the order they were sent.",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "type": "array", "items": { "$ref": "#/components/schemas/NoteResult" } }
+                                }
+                            }
+                        },
+                        "413": {
+                            "description": "More messages than the server accepts at once.",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object",
+                                        "properties": { "max": { "type": "integer" } }
+                                    }
+                                }
+                            }
+                        },
+                        "500": { "description": "The thank-yous couldn't be stored, none were collected." }
+                    }
+                }
+            },
+            "/identity": {
+                "put": {
+                    "summary": "Set the public display name of a key, signed with that key.",
+                    "description": "Only a newer signature than the one the display name was set with replaces it.",
+                    "parameters": [public_key_header, signature_header, timestamp_header],
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": identity } }
+                    },
+                    "responses": {
+                        "204": { "description": "The display name was set." },
+                        "400": {
+                            "description": "The identity didn't pass validation.",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/ValidationErrors" }
+                                }
+                            }
+                        },
+                        "401": message_response("The signature is missing, too old or doesn't match the identity."),
+                        "409": message_response("The display name was set with a newer signature already."),
+                        "500": { "description": "The display name couldn't be stored." }
+                    }
+                }
+            },
+            "/identity/{public_key}": {
+                "get": {
+                    "summary": "The display name of a key.",
+                    "parameters": [public_key],
+                    "responses": {
+                        "200": {
+                            "description": "The identity.",
+                            "content": { "application/json": { "schema": identity } }
+                        },
+                        "404": message_response("No one set a display name for this key."),
+                        "500": { "description": "The database couldn't be queried." }
+                    }
+                }
+            },
+            "/claim": {
+                "post": {
+                    "summary": "Claim to maintain a program.",
+                    "description": "Publish the challenge where the proof points to, then verify the claim. The token works for the program once the claim is verified.",
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": claim } }
+                    },
+                    "responses": {
+                        "201": {
+                            "description": "The claim was started.",
+                            "content": { "application/json": { "schema": claim_created } }
+                        },
+                        "400": {
+                            "description": "The claim didn't pass validation.",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/ValidationErrors" }
+                                }
+                            }
+                        },
+                        "500": { "description": "The claim couldn't be stored." }
+                    }
+                }
+            },
+            "/claim/{id}/verify": {
+                "post": {
+                    "summary": "Look for the published challenge of a claim.",
+                    "description": "A file proof needs the challenge in the file, a dns proof a TXT record with it at `_ty-challenge.<domain>`. The file has to be at `/.well-known/ty-challenge` on the host of the program's homepage, or at `https://raw.githubusercontent.com/<owner>/<repo>/HEAD/.ty-challenge` for its github repository, and the domain has to be theirs, unless an admin approved the claim.",
+                    "parameters": [claim_id],
+                    "responses": {
+                        "204": { "description": "The claim is verified, its token is a maintainer token now." },
+                        "403": message_response("The proof isn't at a link of the program and no admin approved the claim."),
+                        "404": message_response("There is no claim with this id."),
+                        "422": message_response("The challenge wasn't found, the message says why."),
+                        "500": { "description": "The database couldn't be queried." }
+                    }
+                }
+            },
+            "/claim/{id}/approve": {
+                "post": {
+                    "summary": "Let a claim be verified though its proof isn't at a link of the program.",
+                    "description": "Only with the admin token. The challenge still has to be published.",
+                    "parameters": [claim_id],
+                    "security": [{ "maintainerToken": [] }],
+                    "responses": {
+                        "204": { "description": "The claim is approved." },
+                        "401": message_response("The token isn't the admin token."),
+                        "404": message_response("There is no claim with this id."),
+                        "500": { "description": "The database couldn't be queried." }
+                    }
+                }
+            },
+            "/tool/{name}/private": {
+                "get": {
+                    "summary": "The private notes left for a program, for its maintainers. At most 200, newest first.",
+                    "parameters": [program_name],
+                    "security": [{ "maintainerToken": [] }],
+                    "responses": {
+                        "200": {
+                            "description": "The private notes.",
+                            "content": { "application/json": { "schema": detail } }
+                        },
+                        "401": message_response("The maintainer token is missing or not for this program."),
+                        "500": { "description": "The database couldn't be queried." }
+                    }
+                }
+            },
+            "/tool/{name}/delivery": {
+                "get": {
+                    "summary": "Where the program's thank-yous are delivered to.",
+                    "parameters": [program_name],
+                    "security": [{ "maintainerToken": [] }],
+                    "responses": {
+                        "200": {
+                            "description": "The delivery configuration.",
+                            "content": { "application/json": { "schema": delivery } }
+                        },
+                        "401": message_response("The maintainer token is missing or not for this program."),
+                        "500": { "description": "The database couldn't be queried." }
+                    }
+                },
+                "put": {
+                    "summary": "Configure where the program's thank-yous are delivered to.",
+                    "parameters": [program_name],
+                    "security": [{ "maintainerToken": [] }],
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": delivery } }
+                    },
+                    "responses": {
+                        "204": { "description": "The delivery configuration was replaced." },
+                        "400": {
+                            "description": "The configuration didn't pass validation.",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/ValidationErrors" }
+                                }
+                            }
+                        },
+                        "401": message_response("The maintainer token is missing or not for this program."),
+                        "500": { "description": "The configuration couldn't be stored." }
+                    }
+                }
+            },
+            "/tool/{name}/badge.svg": {
+                "get": {
+                    "summary": "How often a program was thanked, as a badge for READMEs.",
+                    "parameters": [
+                        program_name,
+                        {
+                            "name": "style",
+                            "in": "query",
+                            "schema": { "type": "string", "enum": ["flat", "flat-square"], "default": "flat" }
+                        },
+                        {
+                            "name": "color",
+                            "in": "query",
+                            "description": "A named color like `orange`, or a hex color without the `#`.",
+                            "schema": { "type": "string" }
+                        },
+                        {
+                            "name": "label",
+                            "in": "query",
+                            "description": "The text left of the count, at most 50 characters are shown.",
+                            "schema": { "type": "string", "default": "thanks" }
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "The badge, it may be cached for five minutes.",
+                            "content": { "image/svg+xml": { "schema": { "type": "string" } } }
+                        },
+                        "400": { "description": "The style is unknown." },
+                        "500": { "description": "The database couldn't be queried." }
+                    }
+                }
+            },
+            "/tool/{name}/feed.atom": {
+                "get": {
+                    "summary": "Atom feed of the newest 50 public notes left for a program.",
+                    "parameters": [program_name],
+                    "responses": {
+                        "200": {
+                            "description": "The feed, entries are identified by the id of the note.",
+                            "content": { "application/atom+xml": { "schema": { "type": "string" } } }
+                        },
+                        "500": { "description": "The database couldn't be queried." }
+                    }
+                }
+            },
+            "/feed.atom": {
+                "get": {
+                    "summary": "Atom feed of the newest 50 public notes, for all programs.",
+                    "responses": {
+                        "200": {
+                            "description": "The feed, entries are identified by the id of the note.",
+                            "content": { "application/atom+xml": { "schema": { "type": "string" } } }
+                        },
+                        "500": { "description": "The database couldn't be queried." }
+                    }
+                }
+            },
+            "/tool/{name}/profile": {
+                "put": {
+                    "summary": "Describe the program, with links to its homepage, repository and where to sponsor it.",
+                    "parameters": [program_name],
+                    "security": [{ "maintainerToken": [] }],
+                    "requestBody": {
+                        "required": true,
+                        "content": { "application/json": { "schema": profile } }
+                    },
+                    "responses": {
+                        "204": { "description": "The profile was replaced." },
+                        "400": {
+                            "description": "The profile didn't pass validation.",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/ValidationErrors" }
+                                }
+                            }
+                        },
+                        "401": message_response("The maintainer token is missing or not for this program."),
+                        "500": { "description": "The profile couldn't be stored." }
+                    }
+                }
+            },
+            "/tool/{name}": {
+                "get": {
+                    "summary": "How often a program was thanked, with its profile when json is accepted.",
+                    "parameters": [program_name],
+                    "responses": {
+                        "200": {
+                            "description": "The number of thank-yous.",
+                            "content": {
+                                "text/plain": { "schema": { "type": "integer" } },
+                                "application/json": { "schema": tool }
+                            }
+                        },
+                        "500": { "description": "The database couldn't be queried." }
+                    }
+                }
+            },
+            "/tool/{name}/detail": {
+                "get": {
+                    "summary": "The public notes left for a program with their replies, at most 200, newest first.",
+                    "parameters": [program_name],
+                    "responses": {
+                        "200": {
+                            "description": "The notes.",
+                            "content": { "application/json": { "schema": detail } }
+                        },
+                        "500": { "description": "The database couldn't be queried." }
+                    }
+                }
+            },
+            "/openapi.json": {
+                "get": {
+                    "summary": "This document.",
+                    "responses": {
+                        "200": {
+                            "description": "The OpenAPI description of the v0 api.",
+                            "content": { "application/json": {} }
+                        }
+                    }
+                }
+            }
+        },
+        "components": {
+            "schemas": schemas,
+            "securitySchemes": {
+                "editToken": {
+                    "description": "The token handed out with the created note.",
+                    "type": "http",
+                    "scheme": "bearer"
+                },
+                "maintainerToken": {
+                    "description": "The token handed out with a claim, once it's verified, or the admin token of the server.",
+                    "type": "http",
+                    "scheme": "bearer"
+                }
+            }
+        }
+    })
+}
+
+/// Sends a request for every operation in the spec through the real routes and
+/// checks it is routed and answered with a documented status. No database is
+/// needed, queries fail and have to show up as documented errors.
+#[tokio::test]
+async fn spec_matches_routes() {
+    let routes = crate::test_routes();
+    let spec = spec();
+
+    for (path, item) in spec["paths"].as_object().unwrap() {
+        for (method, operation) in item.as_object().unwrap() {
+            let uri = format!(
+                "/v0{}",
+                path.replace("{name}", "rustc")
+                    .replace("{id}", "1")
+                    .replace("{public_key}", "00")
+            );
+            let request = warp::test::request()
+                .method(&method.to_uppercase())
+                .path(uri.trim_end_matches('/'));
+            // an empty program is rejected before it reaches the database
+            let request = match operation.get("requestBody") {
+                Some(body) if body["content"]["application/json"]["schema"]["type"] == "array" => {
+                    request.json(&json!([{ "program": "" }]))
+                }
+                Some(_) => request.json(&json!({ "program": "" })),
+                None => request,
+            };
+
+            let response = request.reply(&routes).await;
+            let status = response.status().as_u16().to_string();
+            // warp answers unrouted requests in plain text, the handlers' 404s
+            // are json, so a documented 404 can't hide a missing route
+            let is_json = serde_json::from_slice::<Value>(response.body()).is_ok();
+            if (status == "404" || status == "405") && !is_json {
+                panic!("{} {} isn't routed, warp answered {}", method, uri, status);
+            }
+            assert!(
+                operation["responses"].get(&status).is_some(),
+                "{} {} answered with undocumented status {}",
+                method,
+                uri,
+                status
+            );
+        }
+    }
+}
+
+/// Finds the `path!`s of the api in the source of `routes`, with the method
+/// each is filtered on, and checks the spec has them. Routes without a method
+/// filter are only documented for GET.
+#[test]
+fn routes_are_in_spec() {
+    let source = include_str!("main.rs");
+    let start = source.find("let api = ").unwrap();
+    let end = source.find("let ty_api_v0").unwrap();
+    let api = &source[start..end];
+
+    let spec = spec();
+    let documented: Vec<(String, String)> = spec["paths"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .flat_map(|(path, item)| {
+            let path = without_parameter_names(path);
+            item.as_object()
+                .unwrap()
+                .keys()
+                .map(move |method| (path.clone(), method.clone()))
+        })
+        .collect();
+
+    let mut routes = 0;
+    for route in api.split(".or(") {
+        let route = route.trim_start_matches("let api = ");
+        let path = if route.starts_with("warp::path::end()") {
+            "/".to_string()
+        } else if let Some(args) = route.strip_prefix("warp::path!(") {
+            let args = &args[..args.find(')').unwrap()];
+            args.split('/')
+                .map(|segment| match segment.trim() {
+                    literal if literal.starts_with('"') => literal.trim_matches('"').to_string(),
+                    _ => "{}".to_string(),
+                })
+                .fold(String::new(), |path, segment| path + "/" + &segment)
+        } else {
+            panic!("route without path!: {}", route);
+        };
+        let method = ["get", "post", "put", "patch", "delete"]
+            .iter()
+            .find(|method| route.contains(&format!("warp::{}()", method)))
+            .unwrap_or(&"get");
+
+        assert!(
+            documented.contains(&(path.clone(), method.to_string())),
+            "{} {} isn't in the spec",
+            method,
+            path
+        );
+        routes += 1;
+    }
+    assert_eq!(
+        routes,
+        documented.len(),
+        "the spec has operations without a route"
+    );
+}
+
+/// `/note/{id}` becomes `/note/{}`, routes don't name their parameters.
+#[cfg(test)]
+fn without_parameter_names(path: &str) -> String {
+    let mut result = String::new();
+    let mut in_name = false;
+    for c in path.chars() {
+        match c {
+            '{' => {
+                in_name = true;
+                result.push_str("{}");
+            }
+            '}' => in_name = false,
+            c if !in_name => result.push(c),
+            _ => {}
+        }
+    }
+    result
+}
+
+#[test]
+fn spec_has_validation_rules() {
+    let spec = spec();
+    let message = &spec["components"]["schemas"]["ThankYouMessage"];
+    assert_eq!(message["properties"]["program"]["maxLength"], 50);
+    assert_eq!(
+        message["properties"]["note"]["maxLength"],
+        ty_lib::MAX_NOTE_LENGTH
+    );
+}
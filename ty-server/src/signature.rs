@@ -0,0 +1,76 @@
+//! Signed requests, a thank-you or identity signed with the sender's ed25519
+//! key. Signing is optional, unsigned thank-yous are as welcome as ever.
+
+use std::convert::TryFrom;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ed25519_dalek::{PublicKey, Signature, Verifier};
+
+/// How far the clocks of a signed request and the server may be apart, in
+/// seconds.
+const MAX_AGE: i64 = 300;
+
+/// The `Ty-Public-Key`, `Ty-Signature` and `Ty-Timestamp` headers, the
+/// signature is not checked yet.
+#[derive(Debug)]
+pub struct Signed {
+    pub public_key: String,
+    pub signature: String,
+    pub timestamp: i64,
+}
+
+impl Signed {
+    /// The public key, hex encoded the same way every time, if the signature
+    /// over `bytes` is valid.
+    pub fn verify(&self, bytes: &[u8]) -> Option<String> {
+        let public_key = PublicKey::from_bytes(&hex::decode(&self.public_key).ok()?).ok()?;
+        let signature = Signature::try_from(&hex::decode(&self.signature).ok()?[..]).ok()?;
+        public_key.verify(bytes, &signature).ok()?;
+        Some(hex::encode(public_key.as_bytes()))
+    }
+}
+
+/// Whether a request signed at `timestamp` is recent enough to be taken.
+pub fn is_fresh(timestamp: i64) -> bool {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("the clock is after 1970")
+        .as_secs() as i64;
+    (now - timestamp).abs() <= MAX_AGE
+}
+
+#[test]
+fn signatures_are_verified() {
+    use ed25519_dalek::{Keypair, Signer};
+
+    let keypair = Keypair::generate(&mut rand::rngs::OsRng);
+    let signed = Signed {
+        public_key: hex::encode(keypair.public.as_bytes()).to_uppercase(),
+        signature: hex::encode(keypair.sign(b"thank you").to_bytes()),
+        timestamp: 0,
+    };
+    assert_eq!(
+        signed.verify(b"thank you"),
+        Some(hex::encode(keypair.public.as_bytes()))
+    );
+    assert_eq!(signed.verify(b"thank you!"), None);
+
+    let garbled = Signed {
+        public_key: "not hex".to_string(),
+        signature: signed.signature,
+        timestamp: 0,
+    };
+    assert_eq!(garbled.verify(b"thank you"), None);
+}
+
+#[test]
+fn old_signatures_are_stale() {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    assert!(is_fresh(now));
+    assert!(is_fresh(now - MAX_AGE + 10));
+    assert!(!is_fresh(now - MAX_AGE - 10));
+    assert!(!is_fresh(now + MAX_AGE + 10));
+}
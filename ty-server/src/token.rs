@@ -0,0 +1,38 @@
+//! Edit tokens, the secret handed out with a new note to edit or retract it
+//! later. Only their hash is stored.
+
+use hmac::{Hmac, Mac, NewMac};
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+pub fn generate() -> String {
+    hex::encode(rand::thread_rng().gen::<[u8; 32]>())
+}
+
+/// The token of a note sent with an `Idempotency-Key` is derived from the key,
+/// so a replay can answer with the same token without storing it. It's keyed
+/// with the server's `TOKEN_SECRET`, keys are often guessable, uuids or
+/// counters, and the token mustn't be computable from them. Only the hash of
+/// the key is stored.
+pub fn derive(secret: &str, idempotency_key: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_varkey(secret.as_bytes()).expect("hmac takes keys of any length");
+    mac.update(idempotency_key.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+pub fn hash(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[test]
+fn tokens_are_random_and_hashed() {
+    let token = generate();
+    assert_eq!(token.len(), 64);
+    assert_ne!(token, generate());
+    assert_eq!(hash(&token), hash(&token));
+    assert_ne!(hash(&token), token);
+    assert_eq!(derive("secret", "key"), derive("secret", "key"));
+    assert_ne!(derive("secret", "key"), derive("other secret", "key"));
+    assert_ne!(derive("secret", "key"), hash("key"));
+}
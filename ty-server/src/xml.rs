@@ -0,0 +1,33 @@
+//! Text in the xml the server renders, the svg badges and atom feeds.
+
+/// Escapes the markup characters. Control characters xml doesn't allow, not
+/// even escaped, become the replacement character, notes may contain them.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            '\u{0}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => escaped.push('\u{fffd}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[test]
+fn invalid_characters_are_replaced() {
+    assert_eq!(
+        escape("<a href=\"x\">&'</a>"),
+        "&lt;a href=&quot;x&quot;&gt;&amp;&apos;&lt;/a&gt;"
+    );
+    assert_eq!(
+        escape("tab\there\r\nnull\u{0}bell\u{7}"),
+        "tab\there\r\nnull\u{fffd}bell\u{fffd}"
+    );
+    assert_eq!(escape("\u{ffff}ü✓"), "\u{fffd}ü✓");
+}
@@ -1,31 +1,82 @@
-use urlencoding::encode;
+use wasm_bindgen_futures::spawn_local;
 use yew::events::KeyboardEvent;
-use yew::{
-    format::{Json, Nothing},
-    prelude::*,
-    services::fetch::{FetchService, FetchTask, Request, Response},
-};
+use yew::prelude::*;
 use yew::{Component, ComponentLink, Html, InputData, ShouldRender};
+use yew_router::{
+    agent::{RouteAgentDispatcher, RouteRequest},
+    route::Route,
+};
+
+use ty_lib::{is_web_link, Identity, NoteEntry, Profile, Reply, ThankYouDetail, Tool};
 
-use ty_lib::ThankYouDetail;
+use crate::AppRoute;
 
 #[derive(Debug)]
 pub enum Msg {
     GetDetails,
+    ShowTool,
     UpdateQuery(String),
-    ReceiveResponse(Result<ThankYouDetail, anyhow::Error>),
+    ReceiveResponse(Result<ThankYouDetail, ty_client::Error>),
+    ReceiveTool(Result<Tool, ty_client::Error>),
+}
+
+#[derive(Properties, Clone, PartialEq, Debug)]
+pub struct Props {
+    /// Fetches the notes of this program right away, if set.
+    #[prop_or_default]
+    pub program: Option<String>,
 }
 
 #[derive(Debug)]
 pub struct Detail {
-    fetch_task: Option<FetchTask>,
+    props: Props,
+    fetching: bool,
     query: String,
     detail: Option<ThankYouDetail>,
+    profile: Option<Profile>,
     link: ComponentLink<Self>,
+    router: RouteAgentDispatcher,
     error: Option<String>,
 }
 
 impl Detail {
+    fn view_profile(&self) -> Html {
+        let profile = match self.profile {
+            Some(ref profile) => profile,
+            None => return html! {},
+        };
+
+        // the server only takes web links, profiles stored before might not be
+        let link = |label: &str, url: &Option<String>| match url {
+            Some(url) if is_web_link(url) => html! { <li><a href=url.clone()>{ label }</a></li> },
+            _ => html! {},
+        };
+        let sponsor = |url: &String| {
+            html! { <li><a href=url.clone()>{ "Sponsor" }</a></li> }
+        };
+
+        let logo = match profile.logo {
+            Some(ref logo) if is_web_link(logo) => html! { <img src=logo.clone() alt="logo"/> },
+            _ => html! {},
+        };
+        let description = match profile.description {
+            Some(ref description) => html! { <p>{ description }</p> },
+            None => html! {},
+        };
+
+        html! {
+          <>
+            { logo }
+            { description }
+            <ul>
+              { link("Homepage", &profile.homepage) }
+              { link("Repository", &profile.repository) }
+              { for profile.funding.iter().filter(|url| is_web_link(url)).map(sponsor) }
+            </ul>
+          </>
+        }
+    }
+
     fn view_detail(&self) -> Html {
         match self.detail {
             Some(ref detail) => {
@@ -36,12 +87,48 @@ impl Detail {
                       </li>
                     }
                 };
+                let reply = |reply: &Reply| {
+                    html! {
+                      <li class="reply">
+                        { &reply.reply }
+                      </li>
+                    }
+                };
+                let signer = |signer: &Identity| {
+                    let name = match signer.display_name {
+                        Some(ref display_name) => display_name.clone(),
+                        None => {
+                            format!("{}…", &signer.public_key[..signer.public_key.len().min(8)])
+                        }
+                    };
+                    html! {
+                      <span class="signer" title={ signer.public_key.clone() }>{" — "} { name }</span>
+                    }
+                };
+                let entry = |entry: &NoteEntry| {
+                    html! {
+                      <li>
+                        { &entry.note }
+                        { for entry.signer.iter().map(signer) }
+                        <ul>
+                          { for entry.replies.iter().map(reply) }
+                        </ul>
+                      </li>
+                    }
+                };
+
+                // servers before the replies only answer with the notes
+                let list = if detail.entries.is_empty() {
+                    html! { for detail.notes.iter().map(notes) }
+                } else {
+                    html! { for detail.entries.iter().map(entry) }
+                };
 
                 html! {
                   <>
                     <p>{"Notes for "} <em>{ detail.program.clone() }</em> </p>
                     <ul>
-                      { for detail.notes.iter().map(notes)}
+                      { list }
                     </ul>
                   </>
                 }
@@ -53,7 +140,7 @@ impl Detail {
     }
 
     fn view_fetching(&self) -> Html {
-        if self.fetch_task.is_some() {
+        if self.fetching {
             html! { <p>{ "Fetching data..." }</p> }
         } else {
             html! {}
@@ -71,20 +158,36 @@ impl Detail {
 
 impl Component for Detail {
     type Message = Msg;
-    type Properties = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        if props.program.is_some() {
+            link.send_message(Msg::GetDetails);
+        }
 
-    fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
         Self {
-            fetch_task: None,
-            query: "".to_string(),
+            query: props.program.clone().unwrap_or_default(),
+            props,
+            fetching: false,
             detail: None,
+            profile: None,
             link,
+            router: RouteAgentDispatcher::new(),
             error: None,
         }
     }
 
-    fn change(&mut self, _props: Self::Properties) -> ShouldRender {
-        false
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props == props {
+            return false;
+        }
+
+        if let Some(ref program) = props.program {
+            self.query = program.clone();
+            self.link.send_message(Msg::GetDetails);
+        }
+        self.props = props;
+        true
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
@@ -94,23 +197,21 @@ impl Component for Detail {
                 self.query = query;
                 true
             }
+            ShowTool => {
+                let route = Route::from(AppRoute::tool(&self.query));
+                self.router.send(RouteRequest::ChangeRoute(route));
+                false
+            }
             GetDetails => {
-                let url = format!(
-                    "{}/v0/tool/{}/detail",
-                    super::BASEURL.clone(),
-                    encode(&self.query)
-                );
-                let request = Request::get(url)
-                    .body(Nothing)
-                    .expect("Could not build request.");
-                let callback = self.link.callback(
-                    |response: Response<Json<Result<ThankYouDetail, anyhow::Error>>>| {
-                        let Json(data) = response.into_body();
-                        Msg::ReceiveResponse(data)
-                    },
-                );
-                let task = FetchService::fetch(request, callback).expect("failed to start request");
-                self.fetch_task = Some(task);
+                let client = super::client();
+                let link = self.link.clone();
+                let program = self.query.clone();
+                spawn_local(async move {
+                    link.send_message(Msg::ReceiveResponse(client.detail(&program).await));
+                    link.send_message(Msg::ReceiveTool(client.tool(&program).await));
+                });
+                self.fetching = true;
+                self.profile = None;
                 self.error = None;
                 true
             }
@@ -121,10 +222,15 @@ impl Component for Detail {
                     }
                     Err(error) => self.error = Some(error.to_string()),
                 }
-                self.fetch_task = None;
+                self.fetching = false;
                 self.error = None;
                 true
             }
+            ReceiveTool(response) => {
+                // a missing profile is no reason to bother anyone
+                self.profile = response.ok().map(|tool| tool.profile);
+                true
+            }
         }
     }
 
@@ -137,13 +243,14 @@ impl Component for Detail {
               oninput=self.link.callback(|e: InputData| Msg::UpdateQuery(e.value))
               onkeypress=self.link.batch_callback(|e: KeyboardEvent| {
                 if e.key() == "Enter" {
-                  vec![Msg::GetDetails]
+                  vec![Msg::ShowTool]
                 } else {
                   vec![]
                 }
               })
             />
             { self.view_fetching() }
+            { self.view_profile() }
             { self.view_detail()}
             { self.view_error() }
           </>
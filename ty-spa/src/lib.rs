@@ -1,19 +1,58 @@
 use comrak::{markdown_to_html, ComrakOptions};
+use urlencoding::{decode, encode};
 use wasm_bindgen::prelude::*;
 use yew::{prelude::*, Component, ComponentLink, Html, ShouldRender};
+use yew_router::{prelude::*, Switch};
 
 mod detail;
 mod list;
+mod submit;
 
 use detail::Detail;
 use list::FetchServiceExample;
+use submit::Submit;
 
 use lazy_static::lazy_static;
 
 lazy_static! {
-    static ref BASEURL: String = "https://ty.paulweissenbach.com".into();
+    static ref BASEURL: String = api_base_url();
 }
 
+fn client() -> ty_client::Client {
+    ty_client::Client::new(format!("{}/v0", *BASEURL))
+}
+
+/// The api is expected on the same origin the app is served from, unless the page
+/// sets `<meta name="ty-api-base" content="https://...">`.
+fn api_base_url() -> String {
+    let configured = yew::utils::document()
+        .query_selector("meta[name='ty-api-base']")
+        .ok()
+        .flatten()
+        .and_then(|meta| meta.get_attribute("content"))
+        .map(|base| base.trim_end_matches('/').to_string())
+        .filter(|base| !base.is_empty());
+
+    configured.unwrap_or_else(|| yew::utils::origin().expect("page has no origin"))
+}
+
+#[derive(Switch, Debug, Clone)]
+pub enum AppRoute {
+    /// Holds the url encoded program name, use `AppRoute::tool` to build it.
+    #[to = "/tool/{name}"]
+    Tool(String),
+    #[to = "/"]
+    Home,
+}
+
+impl AppRoute {
+    pub fn tool(program: &str) -> Self {
+        AppRoute::Tool(encode(program))
+    }
+}
+
+pub type AppAnchor = RouterAnchor<AppRoute>;
+
 struct Model {}
 
 impl Component for Model {
@@ -33,21 +72,43 @@ impl Component for Model {
     }
 
     fn view(&self) -> Html {
-        // propably not a good idea to keep this in the view fn
-        let readme = yew::utils::document().create_element("div").unwrap();
-        readme.set_inner_html(&markdown_to_html(
-            include_str!("../../README.md"),
-            &ComrakOptions::default(),
-        ));
+        html! {
+            <Router<AppRoute> render=Router::render(switch) />
+        }
+    }
+}
 
-        let readme_html = Html::VRef(readme.into());
+fn switch(route: AppRoute) -> Html {
+    match route {
+        AppRoute::Home => {
+            // propably not a good idea to keep this in the view fn
+            let readme = yew::utils::document().create_element("div").unwrap();
+            readme.set_inner_html(&markdown_to_html(
+                include_str!("../../README.md"),
+                &ComrakOptions::default(),
+            ));
 
-        html! {
-            <>
-                {readme_html}
-                <FetchServiceExample />
-                <Detail />
-            </>
+            let readme_html = Html::VRef(readme.into());
+
+            html! {
+                <>
+                    {readme_html}
+                    <Submit />
+                    <FetchServiceExample />
+                    <Detail />
+                </>
+            }
+        }
+        AppRoute::Tool(name) => {
+            let program = decode(&name).unwrap_or(name);
+
+            html! {
+                <>
+                    <AppAnchor route=AppRoute::Home>{"← all programs"}</AppAnchor>
+                    <Detail program=Some(program.clone()) />
+                    <Submit program=Some(program) />
+                </>
+            }
         }
     }
 }
@@ -1,22 +1,18 @@
-// requires the serde and anyhow crates
-
 use ty_lib::ThankYouStats;
-use yew::{
-    format::{Json, Nothing},
-    prelude::*,
-    services::fetch::{FetchService, FetchTask, Request, Response},
-};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::{AppAnchor, AppRoute};
 
 #[derive(Debug)]
 pub enum Msg {
-    ReceiveResponse(Result<Vec<ThankYouStats>, anyhow::Error>),
+    ReceiveResponse(Result<Vec<ThankYouStats>, ty_client::Error>),
 }
 
 #[derive(Debug)]
 pub struct FetchServiceExample {
-    fetch_task: Option<FetchTask>,
+    fetching: bool,
     list: Option<Vec<ThankYouStats>>,
-    link: ComponentLink<Self>,
     error: Option<String>,
 }
 
@@ -29,7 +25,8 @@ impl FetchServiceExample {
                 let list_element = |stats: &ThankYouStats| {
                     html! {
                       <li>
-                        { stats.program.clone() } <span style="color: #bbb; margin-left: 12px;">{"tys: "} { stats.count } {", notes: "} { stats.note_count }</span>
+                        <AppAnchor route=AppRoute::tool(&stats.program)>{ stats.program.clone() }</AppAnchor>
+                        <span style="color: #bbb; margin-left: 12px;">{"tys: "} { stats.count } {", notes: "} { stats.note_count }</span>
                       </li>
                     }
                 };
@@ -50,7 +47,7 @@ impl FetchServiceExample {
         }
     }
     fn view_fetching(&self) -> Html {
-        if self.fetch_task.is_some() {
+        if self.fetching {
             html! { <p>{ "Fetching data..." }</p> }
         } else {
             html! { <p></p> }
@@ -69,21 +66,14 @@ impl Component for FetchServiceExample {
     type Properties = ();
 
     fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        let request = Request::get(super::BASEURL.clone() + "/v0")
-            .body(Nothing)
-            .expect("Could not build request.");
-        let callback = link.callback(
-            |response: Response<Json<Result<Vec<ThankYouStats>, anyhow::Error>>>| {
-                let Json(data) = response.into_body();
-                Msg::ReceiveResponse(data)
-            },
-        );
-        let task = FetchService::fetch(request, callback).expect("failed to start request");
+        let client = super::client();
+        spawn_local(async move {
+            link.send_message(Msg::ReceiveResponse(client.stats().await));
+        });
 
         Self {
-            fetch_task: Some(task),
+            fetching: true,
             list: None,
-            link,
             error: None,
         }
     }
@@ -103,7 +93,7 @@ impl Component for FetchServiceExample {
                     }
                     Err(error) => self.error = Some(error.to_string()),
                 }
-                self.fetch_task = None;
+                self.fetching = false;
                 // we want to redraw so that the page displays the location of the ISS instead of
                 // 'fetching...'
                 true
@@ -0,0 +1,193 @@
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+use yew::{Component, ComponentLink, Html, InputData, ShouldRender};
+
+use ty_client::{Error, FieldErrors};
+use ty_lib::{NoteCreated, ThankYouMessage, MAX_NOTE_LENGTH};
+
+#[derive(Debug)]
+pub enum Msg {
+    UpdateProgram(String),
+    UpdateNote(String),
+    Submit,
+    ReceiveResponse(Result<Option<NoteCreated>, Error>),
+}
+
+#[derive(Properties, Clone, PartialEq, Debug)]
+pub struct Props {
+    /// Prefills the program name, if set.
+    #[prop_or_default]
+    pub program: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Submit {
+    props: Props,
+    sending: bool,
+    program: String,
+    note: String,
+    link: ComponentLink<Self>,
+    field_errors: FieldErrors,
+    error: Option<String>,
+    sent: bool,
+}
+
+impl Submit {
+    fn message(&self) -> ThankYouMessage {
+        let note = self.note.trim();
+        ThankYouMessage {
+            program: self.program.trim().to_string(),
+            note: if note.is_empty() {
+                None
+            } else {
+                Some(note.to_string())
+            },
+            ..Default::default()
+        }
+    }
+
+    fn view_field_errors(&self, field: &str) -> Html {
+        match self.field_errors.get(field) {
+            Some(messages) => html! {
+              <ul class="errors">
+                { for messages.iter().map(|message| html! { <li>{ message }</li> }) }
+              </ul>
+            },
+            None => html! {},
+        }
+    }
+
+    fn view_program_input(&self) -> Html {
+        html! {
+          <>
+            <p>
+              <input
+                placeholder="program"
+                value=&self.program
+                oninput=self.link.callback(|e: InputData| Msg::UpdateProgram(e.value))
+              />
+            </p>
+            { self.view_field_errors("program") }
+          </>
+        }
+    }
+
+    fn view_note_input(&self) -> Html {
+        html! {
+          <>
+            <p>
+              <textarea
+                placeholder="optional note"
+                value=&self.note
+                oninput=self.link.callback(|e: InputData| Msg::UpdateNote(e.value))
+              />
+              <br />
+              <small>{ self.note.trim().chars().count() } { format!(" / {}", MAX_NOTE_LENGTH) }</small>
+            </p>
+            { self.view_field_errors("note") }
+          </>
+        }
+    }
+
+    fn view_status(&self) -> Html {
+        if self.sending {
+            html! { <p>{ "Sending..." }</p> }
+        } else if let Some(ref error) = self.error {
+            html! { <p>{ error.clone() }</p> }
+        } else if self.sent {
+            html! { <p>{ "Thank you for saying thank you!" }</p> }
+        } else {
+            html! {}
+        }
+    }
+}
+
+impl Component for Submit {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            program: props.program.clone().unwrap_or_default(),
+            props,
+            sending: false,
+            note: "".to_string(),
+            link,
+            field_errors: FieldErrors::new(),
+            error: None,
+            sent: false,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props == props {
+            return false;
+        }
+
+        self.program = props.program.clone().unwrap_or_default();
+        self.props = props;
+        true
+    }
+
+    fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        use Msg::*;
+        match msg {
+            UpdateProgram(program) => {
+                self.program = program;
+                true
+            }
+            UpdateNote(note) => {
+                self.note = note;
+                true
+            }
+            Submit => {
+                let message = self.message();
+                self.sent = false;
+                self.error = None;
+                self.field_errors.clear();
+
+                let client = super::client();
+                let link = self.link.clone();
+                spawn_local(async move {
+                    link.send_message(Msg::ReceiveResponse(client.post_note(&message).await));
+                });
+                self.sending = true;
+                true
+            }
+            ReceiveResponse(response) => {
+                self.sending = false;
+                match response {
+                    Ok(_) => {
+                        self.sent = true;
+                        self.note.clear();
+                    }
+                    Err(Error::Validation(errors)) => self.field_errors = errors,
+                    Err(_) => {
+                        self.error = Some(
+                            "Failed to collect your thank you note. Please try again later."
+                                .to_string(),
+                        )
+                    }
+                }
+                true
+            }
+        }
+    }
+
+    fn view(&self) -> Html {
+        html! {
+          <>
+            <h2>{"Say thank you"}</h2>
+            <form onsubmit=self.link.callback(|e: FocusEvent| {
+              e.prevent_default();
+              Msg::Submit
+            })>
+              { self.view_program_input() }
+              { self.view_note_input() }
+              <button type="submit" disabled=self.sending>{"ty"}</button>
+            </form>
+            { self.view_status() }
+          </>
+        }
+    }
+}